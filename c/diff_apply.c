@@ -239,59 +239,107 @@ static GenericValue *gv_from_proto(const DiffDoc__GenericValue *p) {
 /* ═══════════════════════════════════════════════════════════════════════
  *  txt::Mismatch apply
  *  Mirrors txt::MismatchDocCow<String>::apply in src/txt.rs:
- *    split on '\n' → apply DiffOps → join with '\n'
+ *    split on "\n", "\r\n" and lone '\r' → apply DiffOps → join with
+ *    the same line terminators
  * ═══════════════════════════════════════════════════════════════════════ */
 
-typedef struct { char **data; size_t len, cap; } Lines;
+/* eols[i] is the terminator after data[i]; the last line has none */
+typedef struct { char **data; const char **eols; size_t len, cap; } Lines;
 
-static void lines_push(Lines *l, char *s) {
+static const char *EOL_LF = "\n", *EOL_CRLF = "\r\n", *EOL_CR = "\r";
+
+static void lines_grow(Lines *l) {
     if (l->len == l->cap) {
         size_t cap = l->cap ? l->cap * 2 : 8;
         l->data = realloc(l->data, cap * sizeof(char *));
+        l->eols = realloc(l->eols, cap * sizeof(char *));
         l->cap = cap;
     }
-    l->data[l->len++] = s;
 }
 
 static void lines_free(Lines *l) {
     for (size_t i = 0; i < l->len; i++) free(l->data[i]);
     free(l->data);
+    free(l->eols);
 }
 
 static Lines split_lines(const char *s) {
-    Lines l = {NULL, 0, 0};
+    Lines l = {NULL, NULL, 0, 0};
     for (;;) {
-        const char *nl = strchr(s, '\n');
-        size_t n = nl ? (size_t)(nl - s) : strlen(s);
+        size_t n = strcspn(s, "\r\n");
+        const char *eol = s[n] == '\n' ? EOL_LF
+                        : s[n] != '\r' ? NULL
+                        : s[n + 1] == '\n' ? EOL_CRLF : EOL_CR;
         char *line = malloc(n + 1);
         memcpy(line, s, n);
         line[n] = '\0';
-        lines_push(&l, line);
-        if (!nl) break;
-        s = nl + 1;
+        lines_grow(&l);
+        l.data[l.len] = line;
+        l.eols[l.len++] = eol;
+        if (!eol) break;
+        s += n + strlen(eol);
     }
     return l;
 }
 
 static char *join_lines(const Lines *l) {
-    size_t total = l->len > 0 ? l->len - 1 : 0; /* separators */
-    for (size_t i = 0; i < l->len; i++) total += strlen(l->data[i]);
+    size_t total = 0;
+    for (size_t i = 0; i < l->len; i++)
+        total += strlen(l->data[i]) + (i + 1 < l->len ? strlen(l->eols[i]) : 0);
     char *out = malloc(total + 1);
     char *p = out;
     for (size_t i = 0; i < l->len; i++) {
         size_t n = strlen(l->data[i]);
         memcpy(p, l->data[i], n);
         p += n;
-        if (i + 1 < l->len) *p++ = '\n';
+        if (i + 1 < l->len) {
+            n = strlen(l->eols[i]);
+            memcpy(p, l->eols[i], n);
+            p += n;
+        }
     }
     *p = '\0';
     return out;
 }
 
+/* Terminator of a line inserted at idx: the one after it, or before it at the end. */
+static const char *eol_at(const Lines *l, size_t idx) {
+    if (l->len < 2) return EOL_LF;
+    return l->eols[idx + 1 < l->len ? idx : l->len - 2];
+}
+
+/* Replace `len` bytes at `pos` of the line with `value`. */
+static int replace_range(Lines *l, size_t idx, size_t pos, size_t len,
+                         const char *value, DocError *err)
+{
+    if (idx >= l->len) {
+        *err = make_error("Replace index %zu out of bounds", idx);
+        return -1;
+    }
+    size_t slen = strlen(l->data[idx]);
+    if (pos > slen || len > slen - pos) {
+        *err = make_error("Replace range %zu..%zu out of bounds in line %zu (len %zu)",
+                          pos, pos + len, idx, slen);
+        return -1;
+    }
+    if (strchr(value, '\n') || strchr(value, '\r')) {
+        *err = make_error("Replace value contains end-of-line");
+        return -1;
+    }
+    size_t vlen = strlen(value);
+    char *out = malloc(slen - len + vlen + 1);
+    memcpy(out, l->data[idx], pos);
+    memcpy(out + pos, value, vlen);
+    memcpy(out + pos + vlen, l->data[idx] + pos + len, slen - pos - len + 1);
+    free(l->data[idx]);
+    l->data[idx] = out;
+    return 0;
+}
+
 /*
- * Apply a TxtMismatch to a '\n'-delimited string.
+ * Apply a TxtMismatch to a string.
  * Returns a new heap string on success, NULL + *err on failure.
- * Mirrors apply_diff() in src/txt.rs.
+ * Mirrors apply_op() in src/txt.rs.
  */
 static char *txt_apply(const DiffDoc__TxtMismatch *tm,
                        const char                 *input,
@@ -310,9 +358,14 @@ static char *txt_apply(const DiffDoc__TxtMismatch *tm,
                     lines_free(&lines);
                     return NULL;
                 }
+                /* the terminator after the line, or before the last one */
+                if (idx + 1 == lines.len && idx > 0)
+                    lines.eols[idx - 1] = lines.eols[idx];
                 free(lines.data[idx]);
                 memmove(&lines.data[idx], &lines.data[idx + 1],
                         (lines.len - idx - 1) * sizeof(char *));
+                memmove(&lines.eols[idx], &lines.eols[idx + 1],
+                        (lines.len - idx - 1) * sizeof(char *));
                 lines.len--;
                 break;
             }
@@ -324,14 +377,24 @@ static char *txt_apply(const DiffDoc__TxtMismatch *tm,
                     lines_free(&lines);
                     return NULL;
                 }
-                if (lines.len == lines.cap) {
-                    size_t cap = lines.cap ? lines.cap * 2 : 8;
-                    lines.data = realloc(lines.data, cap * sizeof(char *));
-                    lines.cap = cap;
+                if (strchr(op->insert->value, '\n') || strchr(op->insert->value, '\r')) {
+                    *err = make_error("Insert value contains end-of-line");
+                    lines_free(&lines);
+                    return NULL;
                 }
+                const char *eol = eol_at(&lines, idx);
+                lines_grow(&lines);
                 memmove(&lines.data[idx + 1], &lines.data[idx],
                         (lines.len - idx) * sizeof(char *));
+                memmove(&lines.eols[idx + 1], &lines.eols[idx],
+                        (lines.len - idx) * sizeof(char *));
                 lines.data[idx] = str_dup(op->insert->value);
+                lines.eols[idx] = eol;
+                if (idx == lines.len && idx > 0) {
+                    /* appended line: the terminator goes before it */
+                    lines.eols[idx] = lines.eols[idx - 1];
+                    lines.eols[idx - 1] = eol;
+                }
                 lines.len++;
                 break;
             }
@@ -343,6 +406,11 @@ static char *txt_apply(const DiffDoc__TxtMismatch *tm,
                     lines_free(&lines);
                     return NULL;
                 }
+                if (strchr(op->update->value, '\n') || strchr(op->update->value, '\r')) {
+                    *err = make_error("Update value contains end-of-line");
+                    lines_free(&lines);
+                    return NULL;
+                }
                 free(lines.data[idx]);
                 lines.data[idx] = str_dup(op->update->value);
                 break;
@@ -374,7 +442,47 @@ static char *txt_apply(const DiffDoc__TxtMismatch *tm,
                 break;
             }
 
-            default: break;
+            case DIFF_DOC__DIFF_OP__KIND_DELETE:
+                if (replace_range(&lines, (size_t)op->delete_->index, (size_t)op->delete_->pos,
+                                  (size_t)op->delete_->len, "", err) != 0) {
+                    lines_free(&lines);
+                    return NULL;
+                }
+                break;
+
+            case DIFF_DOC__DIFF_OP__KIND_REPLACE:
+                if (replace_range(&lines, (size_t)op->replace->index, (size_t)op->replace->pos,
+                                  (size_t)op->replace->len, op->replace->value, err) != 0) {
+                    lines_free(&lines);
+                    return NULL;
+                }
+                break;
+
+            case DIFF_DOC__DIFF_OP__KIND_EOL: {
+                size_t idx = (size_t)op->eol->index;
+                const char *v = op->eol->value;
+                const char *eol = strcmp(v, EOL_LF) == 0 ? EOL_LF
+                                : strcmp(v, EOL_CRLF) == 0 ? EOL_CRLF
+                                : strcmp(v, EOL_CR) == 0 ? EOL_CR : NULL;
+                if (!eol) {
+                    *err = make_error("Eol value is not an end-of-line");
+                    lines_free(&lines);
+                    return NULL;
+                }
+                if (idx + 1 >= lines.len) {
+                    *err = make_error("Eol index %zu out of bounds %zu", idx,
+                                      lines.len ? lines.len - 1 : 0);
+                    lines_free(&lines);
+                    return NULL;
+                }
+                lines.eols[idx] = eol;
+                break;
+            }
+
+            default:
+                *err = make_error("Unknown DiffOp kind %d", (int)op->kind_case);
+                lines_free(&lines);
+                return NULL;
         }
     }
 
@@ -466,6 +574,31 @@ static DocError hunk_apply(const DiffDoc__Hunk *h, GenericValue *root) {
                         break;
                     }
 
+                    /*
+                     * Move (map):
+                     *   mirrors fn move_key() in src/generic.rs with `preserve_order`:
+                     *   the entry moves to the position, the value is the same.
+                     */
+                    case DIFF_DOC__HUNK_ACTION__KIND_MOVE: {
+                        const DiffDoc__DocIndex *to_di = act->move;
+                        if (to_di->kind_case != DIFF_DOC__DOC_INDEX__KIND_IDX)
+                            return make_error("index type must match: expected idx");
+                        size_t to = (size_t)to_di->idx, from = 0;
+                        while (from < node->map.len && strcmp(node->map.ents[from].key, name) != 0)
+                            from++;
+                        if (from == node->map.len || to >= node->map.len)
+                            return make_error("Path not found: %s", name);
+                        MapEntry e = node->map.ents[from];
+                        if (from < to)
+                            memmove(&node->map.ents[from], &node->map.ents[from + 1],
+                                    (to - from) * sizeof(MapEntry));
+                        else
+                            memmove(&node->map.ents[to + 1], &node->map.ents[to],
+                                    (from - to) * sizeof(MapEntry));
+                        node->map.ents[to] = e;
+                        break;
+                    }
+
                     default: break;
                 }
                 return (DocError){NULL};
@@ -561,6 +694,10 @@ static DocError hunk_apply(const DiffDoc__Hunk *h, GenericValue *root) {
                         break;
                     }
 
+                    case DIFF_DOC__HUNK_ACTION__KIND_MOVE:
+                        return make_error("Move applies to map key only: %llu",
+                                          (unsigned long long)idx);
+
                     default: break;
                 }
                 return (DocError){NULL};
@@ -605,9 +742,11 @@ int mismatches_apply_mut(const DiffDoc__Mismatches *diff,
 
     switch (diff->kind_case) {
 
-        case DIFF_DOC__MISMATCHES__KIND_PATCH: {
+        case DIFF_DOC__MISMATCHES__KIND_PATCH:
+        case DIFF_DOC__MISMATCHES__KIND_UNIFIED: {
             /* GNU patch text — cannot apply directly in C without libpatch.
-             * The raw text is in diff->patch; hand it to your patch tool. */
+             * The raw text is in diff->patch, the parsed hunks of the
+             * unified diff in diff->unified; hand it to your patch tool. */
             DocError e = make_error(
                 "Patch variant requires an external tool (e.g. GNU patch)");
             PUSH_ERR(e);
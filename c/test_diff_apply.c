@@ -278,6 +278,32 @@ static void test_txt_case3(void) {
     }
 }
 
+/*
+ * txt line terminators and changes within a line
+ *   base   = "one\r\ntwo\r\nthree"
+ *   result = "ne\r\n2\r\nthree\nfour"
+ *
+ *   p: Update{1, "2"}, Insert{3, "four"}, Eol{2, "\n"}, Delete{0, 0, 1}
+ */
+static void test_txt_eol(void) {
+    DiffDoc__DiffOpUpdate upd = {0}; upd.index = 1; upd.value = "2";
+    DiffDoc__DiffOpInsert ins = {0}; ins.index = 3; ins.value = "four";
+    DiffDoc__DiffOpEol    eol = {0}; eol.index = 2; eol.value = "\n";
+    DiffDoc__DiffOpDelete del = {0}; del.index = 0; del.pos = 0; del.len = 1;
+    DiffDoc__DiffOp op1 = {0}; op1.kind_case = DIFF_DOC__DIFF_OP__KIND_UPDATE; op1.update = &upd;
+    DiffDoc__DiffOp op2 = {0}; op2.kind_case = DIFF_DOC__DIFF_OP__KIND_INSERT; op2.insert = &ins;
+    DiffDoc__DiffOp op3 = {0}; op3.kind_case = DIFF_DOC__DIFF_OP__KIND_EOL;    op3.eol    = &eol;
+    DiffDoc__DiffOp op4 = {0}; op4.kind_case = DIFF_DOC__DIFF_OP__KIND_DELETE; op4.delete_ = &del;
+    DiffDoc__DiffOp      *ops[] = {&op1, &op2, &op3, &op4};
+    DiffDoc__TxtMismatch  tm = {0}; tm.n_ops = 4; tm.ops = ops;
+    DiffDoc__Mismatches   p  = {0}; p.kind_case = DIFF_DOC__MISMATCHES__KIND_TEXT; p.text = &tm;
+
+    GenericValue doc = {0}; doc.kind = GV_STRING; doc.string = strdup("one\r\ntwo\r\nthree");
+    apply_txt(&p, &doc);
+    ASSERT_STR_EQ(doc.string, "ne\r\n2\r\nthree\nfour");
+    free(doc.string);
+}
+
 /* ═══════════════════════════════════════════════════════════════════════
  *  doc tests  (mirrors tests/json/mod.rs — apply commutativity)
  * ═══════════════════════════════════════════════════════════════════════ */
@@ -520,6 +546,83 @@ static void test_cow_apply(void) {
     gv_free(result);
 }
 
+/* ═══════════════════════════════════════════════════════════════════════
+ *  Rust-encoded fixtures  (written by test_c_fixtures in src/proto.rs)
+ * ═══════════════════════════════════════════════════════════════════════ */
+
+/* Read and unpack fixtures/<name>.bin; NULL if missing or malformed. */
+static DiffDoc__Mismatches *load_fixture(const char *name) {
+    char path[256];
+    snprintf(path, sizeof path, "fixtures/%s.bin", name);
+    FILE *f = fopen(path, "rb");
+    if (!f) {
+        fprintf(stderr, "  cannot open %s\n", path);
+        return NULL;
+    }
+    uint8_t buf[4096];
+    size_t len = fread(buf, 1, sizeof buf, f);
+    fclose(f);
+    return diff_doc__mismatches__unpack(NULL, len, buf);
+}
+
+static GenericValue *gv_num(const char *s) {
+    GenericValue *g = gv_str(s);
+    g->kind = GV_NUMERIC;
+    return g;
+}
+
+/*
+ * wire_append: the bytes of test_bytes_wire_format
+ *   Append{1, 4, "!"}:  "a\nline\n" -> "a\nline!\n"
+ * txt_eol: line terminators alongside line removal and insertion
+ *   Remove{0}, Insert{1, "2.5"}, Update{0, "TWO"}, Eol{0, "\n"}, Eol{2, "\n"}
+ *   "one\r\ntwo\r\nthree\r\n" -> "TWO\n2.5\r\nthree\n"
+ * doc_move: {"a":1,"b":[1,2],"c":"x"} -> {"c":"y","a":1,"b":[1,2,3]}
+ *   [b,2] Insert(3), [c] Update("y"), [c] Move(Idx(0))
+ */
+static void test_rust_fixtures(void) {
+    static const struct { const char *name, *base, *want; } txt[] = {
+        {"wire_append", "a\nline\n",                "a\nline!\n"},
+        {"txt_eol",     "one\r\ntwo\r\nthree\r\n", "TWO\n2.5\r\nthree\n"},
+    };
+    for (size_t i = 0; i < sizeof txt / sizeof txt[0]; i++) {
+        DiffDoc__Mismatches *p = load_fixture(txt[i].name);
+        ASSERT(p != NULL, txt[i].name);
+        if (!p) continue;
+        GenericValue doc = {0}; doc.kind = GV_STRING; doc.string = strdup(txt[i].base);
+        apply_txt(p, &doc);
+        ASSERT_STR_EQ(doc.string, txt[i].want);
+        free(doc.string);
+        diff_doc__mismatches__free_unpacked(p, NULL);
+    }
+
+    DiffDoc__Mismatches *p = load_fixture("doc_move");
+    ASSERT(p != NULL, "doc_move");
+    if (!p) return;
+    GenericValue *doc = gv_map_new();
+    gv_map_put(doc, "a", gv_num("1"));
+    {
+        GenericValue *b = gv_arr_new();
+        gv_arr_push(b, gv_num("1")); gv_arr_push(b, gv_num("2"));
+        gv_map_put(doc, "b", b);
+    }
+    gv_map_put(doc, "c", gv_str("x"));
+    GenericValue *expected = gv_map_new();
+    gv_map_put(expected, "c", gv_str("y"));
+    gv_map_put(expected, "a", gv_num("1"));
+    {
+        GenericValue *b = gv_arr_new();
+        gv_arr_push(b, gv_num("1")); gv_arr_push(b, gv_num("2")); gv_arr_push(b, gv_num("3"));
+        gv_map_put(expected, "b", b);
+    }
+    apply_doc(p, doc);
+    ASSERT(gv_eq(doc, expected), "doc_move: base+p == result");
+    ASSERT(doc->map.len == 3 && strcmp(doc->map.ents[0].key, "c") == 0, "doc_move: key moved first");
+    gv_free(doc);
+    gv_free(expected);
+    diff_doc__mismatches__free_unpacked(p, NULL);
+}
+
 /* ═══════════════════════════════════════════════════════════════════════
  *  main
  * ═══════════════════════════════════════════════════════════════════════ */
@@ -528,6 +631,7 @@ int main(void) {
     test_txt_case1();
     test_txt_case2();
     test_txt_case3();
+    test_txt_eol();
 
     printf("--- doc tests ---\n");
     test_doc_case1();
@@ -537,6 +641,9 @@ int main(void) {
     printf("--- cow test ---\n");
     test_cow_apply();
 
+    printf("--- rust fixtures ---\n");
+    test_rust_fixtures();
+
     printf("\n%d passed, %d failed\n", g_pass, g_fail);
     return g_fail ? 1 : 0;
 }
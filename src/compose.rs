@@ -0,0 +1,418 @@
+use crate::generic::{DocIndex, GenericValue, Hunk, HunkAction};
+use crate::txt::DiffOp;
+
+/// result of moving later change in front of the earlier one
+enum Step<T> {
+    /// replace the earlier change with the merged one, None if both changes cancel out
+    Merge(Option<T>),
+    /// the earlier change is overwritten by the later one
+    Drop,
+    /// changes are independent, continue with rebased later change
+    Pass,
+    /// keep the later change after the earlier one
+    Stop,
+}
+
+/// state of the later change while moving in front of the earlier changes
+struct Scan {
+    /// path rebased to the document state before the current earlier change
+    path: Vec<DocIndex>,
+    /// passed array changes at the same container, so the later change must not change the array size
+    shifted: bool,
+}
+
+/// squash two sequential hunk lists into one,
+/// the later hunks merge into earlier ones if possible, otherwise keep the order
+pub(crate) fn compose_hunks(first: &[Hunk], second: &[Hunk]) -> Vec<Hunk> {
+    let mut out = first.to_vec();
+    for h in second {
+        if !absorb(&mut out, h) {
+            out.push(h.clone());
+        }
+    }
+    out
+}
+
+fn absorb(out: &mut Vec<Hunk>, h: &Hunk) -> bool {
+    if h.path.is_empty() {
+        return false;
+    }
+    let mut scan = Scan { path: h.path.clone(), shifted: false };
+    for i in (0..out.len()).rev() {
+        match step(&out[i], &h.value, &mut scan) {
+            Step::Merge(Some(m)) => {
+                out[i] = m;
+                return true;
+            }
+            Step::Merge(None) => {
+                out.remove(i);
+                return true;
+            }
+            Step::Drop => {
+                out.remove(i);
+            }
+            Step::Pass => {}
+            Step::Stop => return false,
+        }
+    }
+    false
+}
+
+fn step(p: &Hunk, action: &HunkAction, scan: &mut Scan) -> Step<Hunk> {
+    let lp = p.path.len();
+    let lq = scan.path.len();
+    if lp == 0 {
+        return Step::Stop;
+    }
+    let common = p.path.iter().zip(scan.path.iter()).take_while(|(a, b)| a == b).count();
+
+    if common < lp && common < lq {
+        // diverged paths, the earlier change may shift or swap the later path at the same container
+        if common + 1 == lp && common + 1 == lq
+            && (matches!(p.value, HunkAction::Move(_)) || matches!(action, HunkAction::Move(_))) {
+            return Step::Stop; // key move position depends on other keys of the map
+        }
+        if common + 1 == lp {
+            let q = &mut scan.path[common];
+            if common + 1 == lq && matches!(q, DocIndex::Idx(_)) {
+                scan.shifted = true;
+            }
+            match (&p.path[common], q, &p.value) {
+                (DocIndex::Idx(i), DocIndex::Idx(j), HunkAction::Insert(_) | HunkAction::Clone(_)) if *j > *i => *j -= 1,
+                (DocIndex::Idx(i), DocIndex::Idx(j), HunkAction::Remove) if *j > *i => *j += 1,
+                (DocIndex::Idx(i), DocIndex::Idx(j), HunkAction::Swap(DocIndex::Idx(k))) if *j == *k => *j = *i,
+                (DocIndex::Name(n), DocIndex::Name(j), HunkAction::Swap(DocIndex::Name(k))) if j == k => *j = n.clone(),
+                _ => {}
+            }
+            if matches!(&p.value, HunkAction::Clone(src) if &scan.path[common] == src) {
+                return Step::Stop; // the later change alters the copy source
+            }
+        }
+        return Step::Pass;
+    }
+
+    if common == lp {
+        if lp == lq {
+            same_target(p, action, scan)
+        } else {
+            ancestor_target(p, action, scan)
+        }
+    } else {
+        // the later change targets ancestor of the earlier one
+        match action {
+            HunkAction::Update(_) | HunkAction::Remove => Step::Drop,
+            HunkAction::Insert(_) if matches!(scan.path[lq - 1], DocIndex::Idx(_)) => Step::Pass,
+            HunkAction::Insert(_) => Step::Drop,
+            _ => Step::Stop,
+        }
+    }
+}
+
+/// both changes at the same path
+fn same_target(p: &Hunk, action: &HunkAction, scan: &mut Scan) -> Step<Hunk> {
+    let last = p.path.len() - 1;
+    let merge = |value: HunkAction| Step::Merge(Some(Hunk { path: p.path.clone(), value }));
+
+    if let DocIndex::Idx(i) = p.path[last] {
+        match action {
+            HunkAction::Insert(w) => match &p.value {
+                HunkAction::Remove if !scan.shifted => merge(HunkAction::Update(w.clone())),
+                _ => Step::Stop,
+            },
+            HunkAction::Remove => match &p.value {
+                HunkAction::Insert(_) | HunkAction::Clone(_) if !scan.shifted => Step::Merge(None),
+                HunkAction::Update(_) | HunkAction::UpdateTxt(_) if !scan.shifted => merge(HunkAction::Remove),
+                _ => Step::Stop,
+            },
+            HunkAction::Update(w) => match &p.value {
+                HunkAction::Insert(_) | HunkAction::Clone(_) => merge(HunkAction::Insert(w.clone())),
+                HunkAction::Update(_) | HunkAction::UpdateTxt(_) => merge(HunkAction::Update(w.clone())),
+                _ => shift_by(p, i, scan),
+            },
+            HunkAction::UpdateTxt(ops) => match &p.value {
+                HunkAction::Insert(v) => txt_value(v, ops).map(|v| merge(HunkAction::Insert(v))).unwrap_or(Step::Stop),
+                HunkAction::Update(v) => txt_value(v, ops).map(|v| merge(HunkAction::Update(v))).unwrap_or(Step::Stop),
+                HunkAction::UpdateTxt(o) => merge(HunkAction::UpdateTxt(compose_ops(o, ops))),
+                HunkAction::Clone(_) => Step::Stop,
+                _ => shift_by(p, i, scan),
+            },
+            HunkAction::Swap(_) | HunkAction::Clone(_) | HunkAction::Move(_) => Step::Stop,
+        }
+    } else {
+        match action {
+            HunkAction::Update(_) | HunkAction::Insert(_) | HunkAction::Remove => match &p.value {
+                HunkAction::Swap(_) => Step::Stop,
+                HunkAction::Move(_) if !matches!(action, HunkAction::Remove) => Step::Stop,
+                // the key added back goes to the end of the keys order
+                HunkAction::Remove if !matches!(action, HunkAction::Remove) && cfg!(feature="preserve_order") => Step::Stop,
+                HunkAction::Remove if matches!(action, HunkAction::Remove) => merge(HunkAction::Remove),
+                _ => merge(action.clone()),
+            },
+            HunkAction::UpdateTxt(ops) => match &p.value {
+                HunkAction::Insert(v) | HunkAction::Update(v) =>
+                    txt_value(v, ops).map(|v| merge(HunkAction::Update(v))).unwrap_or(Step::Stop),
+                HunkAction::UpdateTxt(o) => merge(HunkAction::UpdateTxt(compose_ops(o, ops))),
+                _ => Step::Stop,
+            },
+            // the later move overrides the key position only
+            HunkAction::Move(_) => match &p.value {
+                HunkAction::Move(_) => merge(action.clone()),
+                _ => Step::Stop,
+            },
+            HunkAction::Swap(_) | HunkAction::Clone(_) => Step::Stop,
+        }
+    }
+}
+
+/// the earlier change moved array element at the same index, so rebase the later path
+fn shift_by(p: &Hunk, i: usize, scan: &mut Scan) -> Step<Hunk> {
+    let last = scan.path.len() - 1;
+    scan.path[last] = match &p.value {
+        HunkAction::Remove => DocIndex::Idx(i + 1),
+        HunkAction::Swap(DocIndex::Idx(k)) => DocIndex::Idx(*k),
+        _ => return Step::Stop,
+    };
+    scan.shifted = true;
+    Step::Pass
+}
+
+/// the earlier change at ancestor path of the later one
+fn ancestor_target(p: &Hunk, action: &HunkAction, scan: &mut Scan) -> Step<Hunk> {
+    let last = p.path.len() - 1;
+    match &p.value {
+        HunkAction::Update(v) | HunkAction::Insert(v) => {
+            let mut v = v.clone();
+            let h = Hunk { path: scan.path[last + 1..].to_vec(), value: action.clone() };
+            if h.apply(&mut v).is_err() {
+                return Step::Stop;
+            }
+            let value = if matches!(p.value, HunkAction::Insert(_)) { HunkAction::Insert(v) } else { HunkAction::Update(v) };
+            Step::Merge(Some(Hunk { path: p.path.clone(), value }))
+        }
+        HunkAction::Remove => match p.path[last] {
+            DocIndex::Idx(i) => {
+                scan.path[last] = DocIndex::Idx(i + 1);
+                Step::Pass
+            }
+            DocIndex::Name(_) => Step::Stop,
+        },
+        HunkAction::Swap(k) => {
+            if matches!((&p.path[last], k), (DocIndex::Idx(_), DocIndex::Idx(_)) | (DocIndex::Name(_), DocIndex::Name(_))) {
+                scan.path[last] = k.clone();
+                Step::Pass
+            } else {
+                Step::Stop
+            }
+        }
+        // the key moves, but the value is the same
+        HunkAction::Move(_) => Step::Pass,
+        HunkAction::UpdateTxt(_) | HunkAction::Clone(_) => Step::Stop,
+    }
+}
+
+fn txt_value(v: &GenericValue, ops: &[DiffOp]) -> Option<GenericValue> {
+    if let GenericValue::StringValue(s) = v {
+        crate::txt::Mismatch(ops.to_vec()).apply_invert(s).ok()
+            .map(|(s, _)| GenericValue::StringValue(s))
+    } else {
+        None
+    }
+}
+
+/// squash two sequential text diff op lists into one,
+/// the later ops merge into earlier ones if possible, otherwise keep the order
+pub(crate) fn compose_ops(first: &[DiffOp], second: &[DiffOp]) -> Vec<DiffOp> {
+    let mut out = first.to_vec();
+    for op in second {
+        if !absorb_op(&mut out, op) {
+            out.push(op.clone());
+        }
+    }
+    out
+}
+
+fn absorb_op(out: &mut Vec<DiffOp>, op: &DiffOp) -> bool {
+    let mut j = op.index();
+    let mut shifted = false;
+    for i in (0..out.len()).rev() {
+        match step_op(&out[i], op, &mut j, shifted) {
+            Step::Merge(Some(m)) => {
+                out[i] = m;
+                return true;
+            }
+            Step::Merge(None) => {
+                out.remove(i);
+                return true;
+            }
+            Step::Drop => {
+                out.remove(i);
+            }
+            Step::Pass => shifted = true,
+            Step::Stop => return false,
+        }
+    }
+    false
+}
+
+fn step_op(p: &DiffOp, op: &DiffOp, j: &mut usize, shifted: bool) -> Step<DiffOp> {
+    let i = p.index();
+    // the end-of-line moves with the inserted and removed lines, keep the order
+    if matches!(p, DiffOp::Eol { .. }) || matches!(op, DiffOp::Eol { .. }) {
+        return match (p, op) {
+            (DiffOp::Eol { .. }, DiffOp::Eol { value, .. }) if i == *j => Step::Merge(Some(DiffOp::Eol { index: i, value: value.clone() })),
+            (DiffOp::Insert { .. } | DiffOp::Remove { .. }, _) | (_, DiffOp::Insert { .. } | DiffOp::Remove { .. }) => Step::Stop,
+            _ => Step::Pass,
+        };
+    }
+    if i != *j {
+        match p {
+            DiffOp::Insert { .. } if *j > i => *j -= 1,
+            DiffOp::Remove { .. } if *j > i => *j += 1,
+            _ => {}
+        }
+        return Step::Pass;
+    }
+    let index = i;
+    match op {
+        DiffOp::Insert { .. } => match p {
+            // the removed end-of-line may differ from the inserted one
+            DiffOp::Remove { .. } => Step::Stop,
+            _ => Step::Pass, // insert in front of the earlier changed line
+        },
+        DiffOp::Remove { .. } => match p {
+            _ if shifted => Step::Stop,
+            DiffOp::Insert { .. } => Step::Merge(None),
+            DiffOp::Update { .. } | DiffOp::Append { .. } | DiffOp::Delete { .. } | DiffOp::Replace { .. } =>
+                Step::Merge(Some(DiffOp::Remove { index })),
+            DiffOp::Remove { .. } | DiffOp::Eol { .. } => Step::Stop,
+        },
+        DiffOp::Update { value, .. } => match p {
+            DiffOp::Insert { .. } => Step::Merge(Some(DiffOp::Insert { index, value: value.clone() })),
+            DiffOp::Update { .. } => Step::Merge(Some(DiffOp::Update { index, value: value.clone() })),
+            DiffOp::Append { .. } | DiffOp::Delete { .. } | DiffOp::Replace { .. } => Step::Drop,
+            DiffOp::Eol { .. } => Step::Stop,
+            DiffOp::Remove { .. } => {
+                *j += 1;
+                Step::Pass
+            }
+        },
+        DiffOp::Append { .. } | DiffOp::Delete { .. } | DiffOp::Replace { .. } => match p {
+            DiffOp::Insert { value: line, .. } => edit_at(line, op)
+                .map(|value| Step::Merge(Some(DiffOp::Insert { index, value }))).unwrap_or(Step::Stop),
+            DiffOp::Update { value: line, .. } => edit_at(line, op)
+                .map(|value| Step::Merge(Some(DiffOp::Update { index, value }))).unwrap_or(Step::Stop),
+            DiffOp::Remove { .. } => {
+                *j += 1;
+                Step::Pass
+            }
+            DiffOp::Append { .. } | DiffOp::Delete { .. } | DiffOp::Replace { .. } | DiffOp::Eol { .. } => Step::Stop,
+        },
+        DiffOp::Eol { .. } => Step::Stop,
+    }
+}
+
+/// the line with the change within the line applied
+fn edit_at(line: &str, op: &DiffOp) -> Option<String> {
+    let (pos, len, value) = match op {
+        DiffOp::Append { pos, value, .. } => (*pos, 0, value.as_str()),
+        DiffOp::Delete { pos, len, .. } => (*pos, *len, ""),
+        DiffOp::Replace { pos, len, value, .. } => (*pos, *len, value.as_str()),
+        _ => return None,
+    };
+    let end = pos.checked_add(len)?;
+    if end > line.len() || !line.is_char_boundary(pos) || !line.is_char_boundary(end) || value.contains('\n') || value.contains('\r') {
+        return None;
+    }
+    Some(format!("{}{}{}", &line[..pos], value, &line[end..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{diff, txt, MismatchDoc, MismatchDocCow, MismatchDocMut};
+    use crate::generic::{from_json, GenericValue};
+
+    /// simple deterministic generator of test documents
+    struct Lcg(u64);
+    impl Lcg {
+        fn next(&mut self, n: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((self.0 >> 33) % n as u64) as usize
+        }
+
+        fn mutate(&mut self, v: &[String]) -> Vec<String> {
+            let mut v = v.to_vec();
+            for _ in 0..self.next(4) {
+                let at = self.next(v.len() + 1);
+                match self.next(4) {
+                    0 if at < v.len() => { v.remove(at); }
+                    1 if at < v.len() => v[at].push('x'),
+                    2 if at < v.len() => v[at] = format!("u{}", self.next(5)),
+                    _ => v.insert(at, format!("i{}", self.next(5))),
+                }
+            }
+            v
+        }
+    }
+
+    /// array of strings and records
+    fn doc(v: &[String]) -> GenericValue {
+        let item = |s: &String| if s.starts_with('u') {
+            GenericValue::Map([("k".to_string(), GenericValue::StringValue(s.clone()))].into())
+        } else {
+            GenericValue::StringValue(s.clone())
+        };
+        GenericValue::Map([("l".to_string(), GenericValue::Array(v.iter().map(item).collect()))].into())
+    }
+
+    fn check_doc(base: &GenericValue, a: &GenericValue, b: &GenericValue) {
+        let p1 = diff::Mismatch::new(base, a).unwrap();
+        let p2 = diff::Mismatch::new(a, b).unwrap();
+        let p = diff::Mismatch::compose(&p1, &p2);
+        assert!(p.len() <= p1.len() + p2.len());
+        let mut doc = base.clone();
+        p.apply_tx(&mut doc).unwrap_or_else(|e| panic!("{e}\n{p1:?}\n{p2:?}\n{p:?}"));
+        assert_eq!(&doc, b, "\n{:?}\n{:?}\n{:?}", p1, p2, p);
+    }
+
+    #[test]
+    fn test_compose_doc() {
+        let base = from_json(r#"{"a": "b", "c": {"d": [1, 2, 3]}, "e": "f"}"#).unwrap();
+        let a = from_json(r#"{"a": "b1", "c": {"d": [1, 3]}, "e": "f", "g": {"h": 1}}"#).unwrap();
+        let b = from_json(r#"{"a": "b2", "c": {"d": [1, 3, 4]}, "g": {"h": 2}}"#).unwrap();
+        let p1 = diff::Mismatch::new(&base, &a).unwrap();
+        let p2 = diff::Mismatch::new(&a, &b).unwrap();
+        let p = diff::Mismatch::compose(&p1, &p2);
+        // "a" and "g" updates are squashed
+        assert_eq!(p.len(), p1.len() + p2.len() - 2, "{:?}", p);
+        check_doc(&base, &a, &b);
+    }
+
+    #[test]
+    fn test_compose_insert_update() {
+        let base = from_json(r#"{"l": ["a", "b"]}"#).unwrap();
+        let a = from_json(r#"{"l": ["a", {"k": 1}, "b"]}"#).unwrap();
+        let b = from_json(r#"{"l": ["a", {"k": 2}, "b"]}"#).unwrap();
+        let p1 = diff::Mismatch::new(&base, &a).unwrap();
+        let p2 = diff::Mismatch::new(&a, &b).unwrap();
+        let p = diff::Mismatch::compose(&p1, &p2);
+        assert_eq!(p, diff::Mismatch::new(&base, &b).unwrap());
+    }
+
+    #[test]
+    fn test_compose_random() {
+        let mut r = Lcg(7);
+        for _ in 0..500 {
+            let base: Vec<String> = (0..r.next(6)).map(|i| format!("v{}", i % 3)).collect();
+            let a = r.mutate(&base);
+            let b = r.mutate(&a);
+            check_doc(&doc(&base), &doc(&a), &doc(&b));
+
+            let (base, a, b) = (base.join("\n"), a.join("\n"), b.join("\n"));
+            let p1 = txt::Mismatch::new(&base, &a).unwrap();
+            let p2 = txt::Mismatch::new(&a, &b).unwrap();
+            let p = txt::Mismatch::compose(&p1, &p2);
+            assert!(p.len() <= p1.len() + p2.len());
+            assert_eq!(p.apply(&base).unwrap(), b, "\n{:?}\n{:?}\n{:?}", p1, p2, p);
+        }
+    }
+}
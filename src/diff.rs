@@ -1,14 +1,20 @@
 use std::cmp::{max, min};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use serde::{Deserialize, Serialize};
-use crate::{txt, DocError, MismatchDoc, MismatchDocCow, MismatchDocMut};
+use crate::{txt, DocError, MismatchDoc, MismatchDocMut};
+use crate::txt::{Algorithm, Granularity};
 
-use crate::generic::{DocIndex, GenericValue, Hunk, HunkAction};
+use crate::generic::{move_key, remove_key, DocIndex, GenericValue, Hunk, HunkAction, NumericString};
 use crate::vec_processor::Range;
+use crate::compose::compose_hunks;
+use crate::rebase::rebase_hunks;
+use crate::json_patch::{to_hunks, to_operations, Operation};
+use crate::merge_patch::to_merge_patch;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
-pub struct Mismatch(Vec<Hunk>);
+pub struct Mismatch(pub(crate) Vec<Hunk>);
 
 impl MismatchDocMut<GenericValue> for Mismatch {
     fn apply_mut(&self, doc_root: &mut GenericValue, fail_fast: bool) -> Result<Vec<DocError>, DocError> {
@@ -24,10 +30,28 @@ impl MismatchDocMut<GenericValue> for Mismatch {
         }
         Ok(err)
     }
+
+    fn apply_tx(&self, doc_root: &mut GenericValue) -> Result<(), DocError> {
+        let mut undo = Vec::new();
+        for h in &self.0 {
+            match h.apply(doc_root) {
+                Ok(mut u) => undo.append(&mut u),
+                Err(e) => {
+                    for u in undo.iter().rev() {
+                        u.apply(doc_root)
+                            .map_err(|re| DocError::new(format!("{}; rollback failed: {}", e, re)))?;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Hunk {
-    fn apply(&self, doc_root: &mut GenericValue) -> Result<(), DocError> {
+    /// returns hunks to revert the change, must apply in reverse order
+    pub(crate) fn apply(&self, doc_root: &mut GenericValue) -> Result<Vec<Hunk>, DocError> {
         let mut input = doc_root;  // current json node pointer
         // traverse the path
         for (idx, path) in self.path.iter().enumerate() {
@@ -36,24 +60,25 @@ impl Hunk {
                 DocIndex::Name(p) => {
                     if let GenericValue::Map(m) = input {
                         if last_element {
-                            match &self.value {
+                            let undo = match &self.value {
                                 HunkAction::Remove => {
-                                    m.remove(p);
+                                    restore_removed(&self.path, remove_key(m, p))
                                 }
                                 HunkAction::Update(v) => {
-                                    m.insert(p.clone(), v.clone());
+                                    restore(&self.path, m.insert(p.clone(), v.clone()))
                                 }
                                 HunkAction::UpdateTxt(v) => {
                                     if let GenericValue::StringValue(s) = m.get(p)
                                             .ok_or_else(|| DocError::new(format!("Path not found: {}", p)))? {
-                                        let new_v = txt::Mismatch(v.clone()).apply(s)?;
+                                        let (new_v, revert) = txt::Mismatch(v.clone()).apply_invert(s)?;
                                         m.insert(p.clone(),  GenericValue::StringValue(new_v));
+                                        vec![Hunk { path: self.path.clone(), value: HunkAction::UpdateTxt(revert.0) }]
                                     } else {
                                         return Err(DocError::new(format!("Expected string: {}", p)));
                                     }
                                 }
                                 HunkAction::Insert(v) => {
-                                    m.insert(p.clone(), v.clone());
+                                    restore(&self.path, m.insert(p.clone(), v.clone()))
                                 }
                                 HunkAction::Swap(v)
                                 | HunkAction::Clone(v) => {
@@ -61,18 +86,34 @@ impl Hunk {
                                         let a = m.get(vv).ok_or_else(|| DocError::new(format!("Path not found: {}", p)))?;
                                         let x= m.insert(p.clone(), a.clone());
                                         if matches!(&self.value, HunkAction::Swap(_)) {
+                                            let mut other = self.path.clone();
+                                            other.pop();
+                                            other.push(v.clone());
+                                            let mut undo = restore(&self.path, x.clone());
                                             if let Some(x) = x {
-                                                m.insert(vv.clone(), x);
+                                                undo.append(&mut restore(&other, m.insert(vv.clone(), x)));
                                             } else {
-                                                m.remove(vv);
+                                                undo.append(&mut restore_removed(&other, remove_key(m, vv)));
                                             }
+                                            undo
+                                        } else {
+                                            restore(&self.path, x)
                                         }
                                     } else {
                                         return Err(DocError::new(format!("index type must match: {:?}", v)));
                                     }
                                 }
-                            }
-                            return Ok(());
+                                HunkAction::Move(v) => {
+                                    if let DocIndex::Idx(to) = v {
+                                        let from = move_key(m, p, *to)
+                                            .ok_or_else(|| DocError::new(format!("Path not found: {}", p)))?;
+                                        vec![Hunk { path: self.path.clone(), value: HunkAction::Move(DocIndex::Idx(from)) }]
+                                    } else {
+                                        return Err(DocError::new(format!("index type must match: {:?}", v)));
+                                    }
+                                }
+                            };
+                            return Ok(undo);
                         } else {
                             m.get_mut(p).ok_or_else(|| DocError::new(format!("Path not found: {}", p)))?
                         }
@@ -84,41 +125,58 @@ impl Hunk {
                 DocIndex::Idx(p) => {
                     if let GenericValue::Array(m) = input {
                         if last_element {
-                            match &self.value {
+                            let len = m.len();
+                            let in_bounds = if matches!(&self.value, HunkAction::Insert(_) | HunkAction::Clone(_)) {
+                                *p <= len
+                            } else {
+                                *p < len || matches!(&self.value, HunkAction::Swap(_))
+                            };
+                            if !in_bounds {
+                                return Err(DocError::new(format!("Path not found: {}", p)));
+                            }
+                            let value = match &self.value {
                                 HunkAction::Remove => {
-                                    m.remove(*p);
+                                    HunkAction::Insert(m.remove(*p))
                                 }
                                 HunkAction::Update(v) => {
-                                    m[*p] = v.clone();
+                                    HunkAction::Update(std::mem::replace(&mut m[*p], v.clone()))
                                 }
                                 HunkAction::UpdateTxt(v) => {
-                                    if let GenericValue::StringValue(s) = m.get(*p)
-                                            .ok_or_else(|| DocError::new(format!("Path not found: {}", p)))? {
-                                        let new_v = txt::Mismatch(v.clone()).apply(s)?;
+                                    if let GenericValue::StringValue(s) = &m[*p] {
+                                        let (new_v, revert) = txt::Mismatch(v.clone()).apply_invert(s)?;
                                         m[*p] = GenericValue::StringValue(new_v);
+                                        HunkAction::UpdateTxt(revert.0)
                                     } else {
                                         return Err(DocError::new(format!("Expected string field: {}", p)));
                                     }
                                 }
                                 HunkAction::Insert(v) => {
-                                    m.insert(p.clone(), v.clone());
+                                    m.insert(*p, v.clone());
+                                    HunkAction::Remove
                                 }
                                 HunkAction::Swap(v)=> {
                                     if let DocIndex::Idx(vv) = v {
                                         swap(m, p, vv);
+                                        HunkAction::Swap(v.clone())
                                     } else {
                                         return Err(DocError::new(format!("index type must match: {:?}", v)));
                                     }
                                 }
                                 HunkAction::Clone(v) => {
                                     if let DocIndex::Idx(vv) = v {
-                                        copy(m, *p, *vv);
+                                        if !copy(m, *p, *vv) {
+                                            return Ok(vec![]);
+                                        }
+                                        HunkAction::Remove
                                     } else {
                                         return Err(DocError::new(format!("index type must match: {:?}", v)));
                                     }
                                 }
-                            }
-                            return Ok(());
+                                HunkAction::Move(_) => {
+                                    return Err(DocError::new(format!("Move applies to map key only: {}", p)));
+                                }
+                            };
+                            return Ok(vec![Hunk { path: self.path.clone(), value }]);
                         } else {
                             m.get_mut(*p).ok_or_else(|| DocError::new(format!("Path not found: {}", p)))?
                         }
@@ -129,19 +187,37 @@ impl Hunk {
             };
         }
 
-        Ok(())
+        Ok(vec![])
+    }
+}
+
+/// revert map key to the previous value or remove if the key was absent
+fn restore(path: &[DocIndex], old: Option<GenericValue>) -> Vec<Hunk> {
+    vec![Hunk { path: path.to_vec(), value: old.map(HunkAction::Update).unwrap_or(HunkAction::Remove) }]
+}
+
+/// revert removed map key to the previous value and position
+fn restore_removed(path: &[DocIndex], removed: Option<(usize, GenericValue)>) -> Vec<Hunk> {
+    match removed {
+        // undo applies in reverse order: update, then move
+        Some((idx, v)) if cfg!(feature="preserve_order") =>
+            vec![Hunk { path: path.to_vec(), value: HunkAction::Move(DocIndex::Idx(idx)) },
+                 Hunk { path: path.to_vec(), value: HunkAction::Update(v) }],
+        removed => restore(path, removed.map(|(_, v)| v)),
     }
 }
 
-fn copy(vec: &mut Vec<GenericValue>, destination_idx: usize, source_idx: usize) {
+/// returns false if nothing copied
+fn copy(vec: &mut Vec<GenericValue>, destination_idx: usize, source_idx: usize) -> bool {
     let len = vec.len();
     if destination_idx > len || source_idx >= len {
-        return;
+        return false;
     }
     if destination_idx == source_idx {
-        return;
+        return false;
     }
     vec.insert(destination_idx, vec[source_idx].clone());
+    true
 }
 
 fn swap(vec: &mut Vec<GenericValue>, a: &usize, b: &usize) {
@@ -165,34 +241,380 @@ impl MismatchDoc<GenericValue> for Mismatch {
     }
 
     fn is_intersect(&self, input: &Self) -> Result<bool, DocError> {
+        Ok(!self.intersections(input, true).is_empty())
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Mismatch {
+    /// calculate the patch with options, see [MismatchDoc::new]
+    pub fn new_with(base: &GenericValue, input: &GenericValue, options: &DiffOptions) -> Result<Self, DocError> {
+        Ok(Mismatch(GenericValue::diff_with(base, input, &vec![], options)))
+    }
+
+    /// reverse patch to restore the base from the patched document:
+    /// apply(invert(p), apply(p, base)) == base
+    pub fn invert(&self, base: &GenericValue) -> Result<Mismatch, DocError> {
+        let mut doc = base.clone();
+        let mut revert = Vec::new();
+        for h in &self.0 {
+            revert.append(&mut h.apply(&mut doc)?);
+        }
+        revert.reverse();
+        Ok(Mismatch(revert))
+    }
+
+    /// squash sequential patches, where the second calculated against the base with the first applied:
+    /// apply(compose(p1, p2), base) == apply(p2, apply(p1, base))
+    pub fn compose(first: &Mismatch, second: &Mismatch) -> Mismatch {
+        Mismatch(compose_hunks(&first.0, &second.0))
+    }
+
+    /// transform the patch to apply after `onto`, where both calculated against the same base,
+    /// array indexes shift to follow inserts, removes, clones and swaps of `onto`:
+    /// apply(rebase(p_b, p_a), apply(p_a, base)) contains changes of both patches
+    pub fn rebase(&self, onto: &Mismatch) -> Result<Mismatch, Conflict> {
+        rebase_hunks(&self.0, &onto.0).map(Mismatch)
+    }
+
+    /// RFC 6902 JSON Patch operations, the base is required to resolve text updates and swaps:
+    /// `Swap` became two `move` and `Clone` became `copy`; the key `Move` hunks of `preserve_order`
+    /// are dropped as JSON objects are unordered, so the applied operations may give other key order
+    pub fn to_json_patch(&self, base: &GenericValue) -> Result<Vec<Operation>, DocError> {
+        to_operations(&self.0, base)
+    }
+
+    /// patch from RFC 6902 JSON Patch operations, the base is required to resolve JSON Pointers
+    /// to a map key or an array index, and to check `test` operations;
+    /// the operations replacing the whole document at the root pointer `""` fail
+    pub fn from_json_patch(ops: &[Operation], base: &GenericValue) -> Result<Mismatch, DocError> {
+        to_hunks(ops, base).map(Mismatch)
+    }
 
+    /// RFC 7396 JSON Merge Patch if the patch has map paths only,
+    /// the base is required to resolve text updates and swaps
+    pub fn to_merge_patch(&self, base: &GenericValue) -> Result<GenericValue, DocError> {
+        to_merge_patch(&self.0, base)
+    }
+
+    /// hunks of the patch in apply order
+    pub fn hunks(&self) -> &[Hunk] {
+        &self.0
+    }
+
+    /// list of hunk pairs which prevent to apply patches in any order, empty if no intersection
+    pub fn conflicts(&self, other: &Self) -> Vec<Conflict> {
+        self.intersections(other, false)
+    }
+
+    /// stop on first conflict found if first_only
+    fn intersections(&self, input: &Self, first_only: bool) -> Vec<Conflict> {
         let ranges_a = PathRange::new(&self.0);
         let ranges_b = PathRange::new(&input.0);
-
-        for a in &self.0 {
-            for b in &input.0 {
-                if is_intersect(a, &ranges_a, b, &ranges_b, #[cfg(debug_assertions)] "a~b") {
-                    return Ok(true);
+        let mut found = Vec::new();
+
+        for (ia, a) in self.0.iter().enumerate() {
+            for (ib, b) in input.0.iter().enumerate() {
+                if let Some((reason, path)) = is_intersect(a, &ranges_a, b, &ranges_b, #[cfg(debug_assertions)] "a~b")
+                    .or_else(|| is_intersect(b, &ranges_b, a, &ranges_a, #[cfg(debug_assertions)] "b~a")) {
+                    found.push(Conflict { hunk_a: ia, hunk_b: ib, path, reason });
+                    if first_only {
+                        return found;
+                    }
                 }
+            }
+        }
+        found
+    }
+}
 
-                if is_intersect(b, &ranges_b, a, &ranges_a, #[cfg(debug_assertions)] "b~a") {
-                    return Ok(true);
-                }
+/// options to calculate the difference of documents
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// array path pattern with the field name to match array elements by
+    keys: Vec<(Vec<PathSelector>, String)>,
+    numeric: NumericMode,
+    /// the default if not set: any strings of array elements, the long strings of the rest
+    text: Option<TextDiff>,
+    /// path pattern with the text diff of the matched strings
+    texts: Vec<(Vec<PathSelector>, TextDiff)>,
+    /// subtrees to ignore
+    exclude: Vec<Vec<PathSelector>>,
+    /// subtrees to diff only, all if empty
+    include: Vec<Vec<PathSelector>>,
+}
+
+/// numbers comparison of the diff
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NumericMode {
+    /// the numbers are equal if the texts are equal
+    #[default]
+    Text,
+    /// the numbers are equal by the decimal value of any precision, i.e. `1.0`, `1`, `1e0` and `-0`, `0`;
+    /// the base text stays if the value is the same
+    Value,
+}
+
+/// strings comparison of the diff
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextDiff {
+    /// the changed strings with the total length of both above the threshold in bytes are the text diff
+    /// of the lines, the rest are updated as a whole; 0 for any strings, usize::MAX to never diff
+    pub threshold: usize,
+    /// unit of the changes within a line of the text diff
+    pub granularity: Granularity,
+    /// matching of the lines of the text diff
+    pub algorithm: Algorithm,
+}
+
+impl Default for TextDiff {
+    fn default() -> Self {
+        TextDiff { threshold: (u16::MAX >> 4) as usize, granularity: Granularity::default(), algorithm: Algorithm::default() }
+    }
+}
+
+impl TextDiff {
+    /// text diff of the strings with the length above the threshold
+    pub fn new(threshold: usize, granularity: Granularity) -> Self {
+        TextDiff { threshold, granularity, algorithm: Algorithm::default() }
+    }
+
+    /// matching of the lines
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// the ops of the text diff if the strings are long enough, None to update the whole string
+    pub(crate) fn diff(&self, a: &str, b: &str) -> Option<Vec<txt::DiffOp>> {
+        (a.len() + b.len() > self.threshold).then(|| txt::diff(a, b, self.granularity, self.algorithm))
+    }
+}
+
+impl DiffOptions {
+    /// match elements of arrays by the identity field instead of the whole value, i.e. `$.users[*].id`,
+    /// so changes of the matched element become nested hunks under its index
+    pub fn with_key(mut self, pattern: &str) -> Result<Self, DocError> {
+        let mut path = parse_path(pattern)?;
+        match (path.pop(), path.pop()) {
+            (Some(PathSelector::Name(key)), Some(PathSelector::AnyIdx)) => {
+                self.keys.push((path, key));
+                Ok(self)
             }
+            _ => Err(DocError::new(format!("Identity key must end with [*].<name>: {}", pattern))),
         }
+    }
 
-        Ok(false)
+    /// compare numbers by the text or by the value
+    pub fn with_numeric(mut self, mode: NumericMode) -> Self {
+        self.numeric = mode;
+        self
     }
 
-    fn len(&self) -> usize {
-        self.0.len()
+    /// ignore changes of the matched subtrees, i.e. `$.meta.*` or `$..etag` for the key at any level
+    pub fn with_exclude(mut self, pattern: &str) -> Result<Self, DocError> {
+        self.exclude.push(parse_path(pattern)?);
+        Ok(self)
+    }
+
+    /// restrict the diff to the matched subtrees, i.e. `$.spec` or `$.items[*].name`;
+    /// the excludes apply within the included subtrees
+    pub fn with_include(mut self, pattern: &str) -> Result<Self, DocError> {
+        self.include.push(parse_path(pattern)?);
+        Ok(self)
+    }
+
+    /// text diff of the strings, the array elements included
+    pub fn with_text(mut self, text: TextDiff) -> Self {
+        self.text = Some(text);
+        self
+    }
+
+    /// text diff of the strings matched by the path pattern, i.e. `$.items[*].description`,
+    /// the first matched pattern overrides the default
+    pub fn with_text_at(mut self, pattern: &str, text: TextDiff) -> Result<Self, DocError> {
+        self.texts.push((parse_path(pattern)?, text));
+        Ok(self)
+    }
+
+    /// text diff of the strings at the path; the strings of array elements are the text diff
+    /// of any length unless the options set, the same as before the threshold
+    pub(crate) fn text(&self, path: &[DocIndex]) -> TextDiff {
+        if let Some((_, t)) = self.texts.iter().find(|(p, _)| is_match(p, path)) {
+            return *t;
+        }
+        match (self.text, path.last()) {
+            (Some(t), _) => t,
+            (None, Some(DocIndex::Idx(_))) => TextDiff { threshold: 0, ..TextDiff::default() },
+            (None, _) => TextDiff::default(),
+        }
+    }
+
+    pub(crate) fn numeric(&self) -> NumericMode {
+        self.numeric
+    }
+
+    pub(crate) fn numeric_eq(&self, a: &NumericString, b: &NumericString) -> bool {
+        a == b || self.numeric == NumericMode::Value && a.decimal().is_some_and(|d| b.decimal() == Some(d))
+    }
+
+    /// true if any include or exclude pattern is set
+    pub(crate) fn has_filters(&self) -> bool {
+        !self.exclude.is_empty() || !self.include.is_empty()
+    }
+
+    /// true if the changes at the path must be ignored: the path is in an excluded subtree
+    /// or out of the included subtrees and not an ancestor of them
+    pub(crate) fn is_filtered(&self, path: &[DocIndex]) -> bool {
+        let within = |p: &Vec<PathSelector>| (0..=path.len()).any(|i| is_match(p, &path[..i]));
+        self.exclude.iter().any(within)
+            || (!self.include.is_empty() && !self.include.iter().any(|p| within(p) || is_ancestor(p, path)))
+    }
+
+    /// identity field name of the array elements at the path
+    pub(crate) fn key(&self, path: &[DocIndex]) -> Option<&str> {
+        self.keys.iter().find(|(p, _)| is_match(p, path)).map(|(_, k)| k.as_str())
+    }
+}
+
+/// element of the path pattern as `$.name.*[0][*]..name`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PathSelector {
+    Name(String),
+    Idx(usize),
+    AnyName,
+    AnyIdx,
+    /// `..` any number of names or indexes, including none
+    Descendants,
+}
+
+fn parse_path(pattern: &str) -> Result<Vec<PathSelector>, DocError> {
+    let err = || DocError::new(format!("Invalid path pattern: {}", pattern));
+    let mut rest = pattern.strip_prefix('$').ok_or_else(err)?;
+    let mut path = Vec::new();
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix("..") {
+            if r.is_empty() || r.starts_with('.') {
+                return Err(err());
+            }
+            path.push(PathSelector::Descendants);
+            rest = if r.starts_with('[') { r } else { &rest[1..] };
+        } else if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(['.', '[']).unwrap_or(r.len());
+            path.push(match &r[..end] {
+                "" => return Err(err()),
+                "*" => PathSelector::AnyName,
+                name => PathSelector::Name(name.to_string()),
+            });
+            rest = &r[end..];
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').ok_or_else(err)?;
+            path.push(match &r[..end] {
+                "*" => PathSelector::AnyIdx,
+                idx => PathSelector::Idx(idx.parse().map_err(|_| err())?),
+            });
+            rest = &r[end + 1..];
+        } else {
+            return Err(err());
+        }
+    }
+    Ok(path)
+}
+
+pub(crate) fn is_match(pattern: &[PathSelector], path: &[DocIndex]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((PathSelector::Descendants, rest)) => (0..=path.len()).any(|i| is_match(rest, &path[i..])),
+        Some((s, rest)) => path.split_first().is_some_and(|(p, tail)| is_match_one(s, p) && is_match(rest, tail)),
+    }
+}
+
+/// true if the path is a parent of the paths matched by the pattern
+fn is_ancestor(pattern: &[PathSelector], path: &[DocIndex]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (_, None) => true,
+        (None, _) => false,
+        (Some((PathSelector::Descendants, _)), _) => true,
+        (Some((s, rest)), Some((p, tail))) => is_match_one(s, p) && is_ancestor(rest, tail),
+    }
+}
+
+fn is_match_one(selector: &PathSelector, index: &DocIndex) -> bool {
+    match (selector, index) {
+        (PathSelector::Name(a), DocIndex::Name(b)) => a == b,
+        (PathSelector::Idx(a), DocIndex::Idx(b)) => a == b,
+        (PathSelector::AnyName, DocIndex::Name(_)) | (PathSelector::AnyIdx, DocIndex::Idx(_)) => true,
+        _ => false,
+    }
+}
+
+/// false if both hunks are text updates of the same string, which apply in any order
+fn txt_intersect(a: &Hunk, b: &Hunk) -> bool {
+    match (&a.value, &b.value) {
+        (HunkAction::UpdateTxt(a_ops), HunkAction::UpdateTxt(b_ops)) if a.path == b.path =>
+            txt::Mismatch(a_ops.clone()).is_intersect(&txt::Mismatch(b_ops.clone())).unwrap_or(true),
+        _ => true,
+    }
+}
+
+/// pair of hunks from two patches, which prevent to apply patches in any order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Conflict {
+    /// index of the hunk in the first patch
+    pub hunk_a: usize,
+    /// index of the hunk in the other patch
+    pub hunk_b: usize,
+    /// shared path of both hunks where the intersection found
+    pub path: Vec<DocIndex>,
+    pub reason: ConflictReason,
+}
+
+/// cause of the patches intersection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConflictReason {
+    /// array ranges of inserts or removes are overlapped
+    RangeOverlap,
+    /// same map key changed to different values
+    DifferentValues,
+    /// same path is a map key in one patch and an array index in other
+    IndexTypeMismatch,
+    /// both remove array elements with different indexes
+    RemoveRemove,
+    /// both insert different values at same array index
+    InsertInsert,
+    /// both clone different elements to same array index
+    CloneClone,
+    /// same array element changed by update, text update or swap
+    SameElement,
+    /// array elements shift left or right by remove, insert or clone below other action index
+    ShiftedIndex,
+    /// map keys order changed by key move along with other key change of the same map
+    KeyOrder,
+}
+
+impl Display for ConflictReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            ConflictReason::RangeOverlap => "overlap in ranges",
+            ConflictReason::DifferentValues => "diff values",
+            ConflictReason::IndexTypeMismatch => "discrepancy in types name-idx",
+            ConflictReason::RemoveRemove => "both removes with different indexes",
+            ConflictReason::InsertInsert => "both insert with different values",
+            ConflictReason::CloneClone => "both clone with different values",
+            ConflictReason::SameElement => "both change same element",
+            ConflictReason::ShiftedIndex => "another action at shifted index",
+            ConflictReason::KeyOrder => "key move along with other key change",
+        };
+        write!(f, "{}", msg)
     }
 }
 
 #[derive(Debug)]
 struct PathRange {
     /// use as a key
-    #[allow(unused)]
     path: Vec<DocIndex>,
     range: Range,
 }
@@ -263,9 +685,10 @@ impl PathRange {
 }
 
 /// check for intersection of two patches by path for update or delete of documents including vec/array
-fn is_intersect(a: &Hunk, ranges_a: &PathMapType, b: &Hunk, ranges_b: &PathMapType, #[cfg(debug_assertions)] _msg: &str) -> bool {
+/// returns the cause and the shared path of the intersection
+fn is_intersect(a: &Hunk, ranges_a: &PathMapType, b: &Hunk, ranges_b: &PathMapType, #[cfg(debug_assertions)] _msg: &str) -> Option<(ConflictReason, Vec<DocIndex>)> {
     if a.path.len() == 0 || b.path.len() == 0 {
-        return false; // assert no changes
+        return None; // assert no changes
     }
 
     // this is a json path index, the longer path wont intersect with short one if longer do not contain the short
@@ -277,13 +700,13 @@ fn is_intersect(a: &Hunk, ranges_a: &PathMapType, b: &Hunk, ranges_b: &PathMapTy
         if !cmp_val {
             if a.path[i] != b.path[i]
             {
-                return false; // diverged paths
+                return None; // diverged paths
             }
         }
 
-        if let Some(_cause) = is_intersect2(a, b, i, ranges_b) {
-            #[cfg(feature="verbose")] println!("is_intersect {_msg} upto {comp2idx} as step {i} of {comp2idx} by: {_cause}\nBase action: {a}\nInterfere with: {b}");
-            return true;
+        if let Some(cause) = is_intersect2(a, b, i, ranges_b) {
+            #[cfg(feature="verbose")] println!("is_intersect {_msg} upto {comp2idx} as step {i} of {comp2idx} by: {cause}\nBase action: {a}\nInterfere with: {b}");
+            return Some((cause, a.path[..=i].to_vec()));
         }
     }
 
@@ -293,21 +716,21 @@ fn is_intersect(a: &Hunk, ranges_a: &PathMapType, b: &Hunk, ranges_b: &PathMapTy
             for r in x {
                 for p in v {
                     if p.range.overlap(&r.range) {
-                        #[cfg(feature="verbose")] println!("is_intersect {msg} as overlap {p:?} with {:?}", &r.range);
-                        return true;
+                        #[cfg(feature="verbose")] println!("is_intersect {_msg} as overlap {p:?} with {:?}", &r.range);
+                        return Some((ConflictReason::RangeOverlap, p.path.clone()));
                     }
                 }
             }
         }
     }
-    false
+    None
 }
 
 /// check for intersection of two patches by path for update or delete of documents including vec/array
-fn is_intersect2(a: &Hunk, b: &Hunk, idx: usize, ranges_b: &PathMapType) -> Option<&'static str> {
-    fn return_(cnd: bool, msg: &'static str) -> Option<&'static str> {
+fn is_intersect2(a: &Hunk, b: &Hunk, idx: usize, ranges_b: &PathMapType) -> Option<ConflictReason> {
+    fn return_(cnd: bool, reason: ConflictReason) -> Option<ConflictReason> {
         if cnd {
-            Some(msg)
+            Some(reason)
         } else {
             None
         }
@@ -319,7 +742,7 @@ fn is_intersect2(a: &Hunk, b: &Hunk, idx: usize, ranges_b: &PathMapType) -> Opti
             if let Some(ps) = ranges_b.get(&PathKey(a.path[..max(a.path.len() - 2, 0)].to_vec())) {
                 for p in ps {
                     if p.range.start < idx && p.range.end.unwrap_or(usize::MAX) > idx {
-                        return Some("overlap in ranges");
+                        return Some(ConflictReason::RangeOverlap);
                     }
                 }
             }
@@ -329,98 +752,114 @@ fn is_intersect2(a: &Hunk, b: &Hunk, idx: usize, ranges_b: &PathMapType) -> Opti
         match &a.path[idx] {
             DocIndex::Name(a_path) => {
                 match &b.path[idx] {
-                    DocIndex::Name(b_path) =>
-                        return_(a_path == b_path && &a.value != &b.value, "diff values"),
+                    DocIndex::Name(b_path) => {
+                        let is_move = |h: &Hunk| matches!(h.value, HunkAction::Move(_));
+                        let is_txt = |h: &Hunk| matches!(h.value, HunkAction::UpdateTxt(_));
+                        if a.path.len() == b.path.len() && (is_move(a) || is_move(b)) {
+                            // other keys of the map shift by the key move, but not by a text update
+                            let same = a_path == b_path && a.value == b.value;
+                            return_(!(same || is_txt(a) || is_txt(b)), ConflictReason::KeyOrder)
+                        } else {
+                            // the key move does not change the nested values
+                            let short = if a.path.len() < b.path.len() { a } else { b };
+                            return_(a_path == b_path && &a.value != &b.value && !is_move(short) && txt_intersect(a, b), ConflictReason::DifferentValues)
+                        }
+                    }
                     DocIndex::Idx(_) =>
-                        return_(a.value != b.value, "discrepancy in types name-idx, but in case of delete - no matter"),
+                        return_(a.value != b.value, ConflictReason::IndexTypeMismatch),
                 }
             }
             DocIndex::Idx(a_idx) => {
                 match &b.path[idx] {
                     DocIndex::Name(_) =>
-                        return_(a.value != b.value, "discrepancy in types idx-name, but in case of delete - no matter"),
+                        return_(a.value != b.value, ConflictReason::IndexTypeMismatch),
                     DocIndex::Idx(b_idx) => {
                         match &a.value {
                             HunkAction::Remove => { // shift array left
                                 match &b.value {
                                     HunkAction::Remove =>
-                                        return_(a_idx != b_idx, "both removes with different indexes"),
-                                    _ => return_(a_idx < b_idx, "expected to remove but another lower index action found"),
+                                        return_(a_idx != b_idx, ConflictReason::RemoveRemove),
+                                    _ => return_(a_idx < b_idx, ConflictReason::ShiftedIndex),
                                 }
                             }
                             // shift array right
                             HunkAction::Insert(a_val) => {
                                 match &b.value {
                                     HunkAction::Insert(b_val) =>
-                                        return_(a_idx == b_idx && a_val != b_val, "both insert with different values"),
-                                    _ => return_(a_idx < b_idx, "expected to insert but another lower index action found"),
+                                        return_(a_idx == b_idx && a_val != b_val, ConflictReason::InsertInsert),
+                                    _ => return_(a_idx < b_idx, ConflictReason::ShiftedIndex),
                                 }
                             }
                             // shift array right
                             HunkAction::Clone(a_val) => {
                                 match &b.value {
                                     HunkAction::Clone(b_val) =>
-                                        return_(a_idx == b_idx && a_val != b_val, "both clone with different values"),
-                                    _ => return_(a_idx < b_idx, "expected to clone but another lower index action found"),
+                                        return_(a_idx == b_idx && a_val != b_val, ConflictReason::CloneClone),
+                                    _ => return_(a_idx < b_idx, ConflictReason::ShiftedIndex),
                                 }
                             }
                             // no shift actions below
                             HunkAction::Update(a_val) => {
                                 match &b.value {
                                     HunkAction::Update(b_val) =>
-                                        return_(a_idx == b_idx && a_val != b_val, "both update with different values"),
+                                        return_(a_idx == b_idx && a_val != b_val, ConflictReason::SameElement),
                                     HunkAction::UpdateTxt(_) =>
-                                        return_(a_idx == b_idx, "update with other update txt"),
+                                        return_(a_idx == b_idx, ConflictReason::SameElement),
                                     HunkAction::Swap(_) =>
-                                        return_(a_idx == b_idx, "update with other swap"),
+                                        return_(a_idx == b_idx, ConflictReason::SameElement),
                                     // no shift compare to shift actions
                                     HunkAction::Remove =>
-                                        return_(a_idx > b_idx, "update with other remove at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
                                     // shift array right
                                     HunkAction::Insert(_) =>
-                                        return_(a_idx > b_idx, "update with other insert at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
                                     HunkAction::Clone(_) =>
-                                        return_(a_idx > b_idx, "update with other clone at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
+                                    HunkAction::Move(_) => Some(ConflictReason::IndexTypeMismatch),
                                 }
                             }
                             // no shift
                             HunkAction::UpdateTxt(a_val) => {
                                 match &b.value {
                                     HunkAction::UpdateTxt(b_val) =>
-                                        return_(a_idx == b_idx && a_val != b_val, "both update with different values"),
+                                        return_(a_idx == b_idx && a_val != b_val && txt_intersect(a, b), ConflictReason::SameElement),
                                     HunkAction::Update(_) =>
-                                        return_(a_idx == b_idx, "update txt with other update"),
+                                        return_(a_idx == b_idx, ConflictReason::SameElement),
                                     HunkAction::Swap(_) =>
-                                        return_(a_idx == b_idx, "update txt  with other swap"),
+                                        return_(a_idx == b_idx, ConflictReason::SameElement),
                                     // no shift compare to shift actions
                                     HunkAction::Remove =>
-                                        return_(a_idx > b_idx, "update txt with other insert at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
                                     // shift array right
                                     HunkAction::Insert(_) =>
-                                        return_(a_idx > b_idx, "update txt with other insert at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
                                     HunkAction::Clone(_) =>
-                                        return_(a_idx > b_idx, "update txt with other clone at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
+                                    HunkAction::Move(_) => Some(ConflictReason::IndexTypeMismatch),
                                 }
                             }
                             // no shift
                             HunkAction::Swap(a_val) => {
                                 match &b.value {
                                     HunkAction::Swap(b_val) =>
-                                        return_(a_idx == b_idx && a_val != b_val, "both swap with different values"),
+                                        return_(a_idx == b_idx && a_val != b_val, ConflictReason::SameElement),
                                     HunkAction::UpdateTxt(_) =>
-                                        return_(a_idx == b_idx, "swap with other txt update"),
+                                        return_(a_idx == b_idx, ConflictReason::SameElement),
                                     HunkAction::Update(_) =>
-                                        return_(a_idx == b_idx, "swap with other update"),
+                                        return_(a_idx == b_idx, ConflictReason::SameElement),
                                     // no shift A, compare to shift B actions
                                     HunkAction::Remove =>
-                                        return_(a_idx > b_idx, "swap with other remove at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
                                     // shift array right
                                     HunkAction::Insert(_) =>
-                                        return_(a_idx > b_idx, "swap with other insert at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
                                     HunkAction::Clone(_) =>
-                                        return_(a_idx > b_idx, "swap with other clone at lower index"),
+                                        return_(a_idx > b_idx, ConflictReason::ShiftedIndex),
+                                    HunkAction::Move(_) => Some(ConflictReason::IndexTypeMismatch),
                                 }
                             }
+                            // map key only
+                            HunkAction::Move(_) => Some(ConflictReason::IndexTypeMismatch),
                         }
                     }
                 }
@@ -617,6 +1056,66 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_conflicts() {
+        let base = from_str_vec2(vec![("a", "b"), ("c", "d")]);
+        let patch1 = Mismatch::new(&base, &from_str_vec2(vec![("a", "b1"), ("c", "d")])).unwrap();
+        let patch2 = Mismatch::new(&base, &from_str_vec2(vec![("a", "b"), ("c", "d2")])).unwrap();
+        let patch3 = Mismatch::new(&base, &from_str_vec2(vec![("a", "e"), ("c", "d")])).unwrap();
+        assert!(patch1.conflicts(&patch2).is_empty());
+        let c = patch1.conflicts(&patch3);
+        assert_eq!(c, vec![Conflict { hunk_a: 0, hunk_b: 0, path: vec![DocIndex::Name("a".into())], reason: ConflictReason::DifferentValues }]);
+
+        let base = from_str_vec(vec!["a", "b", "c"]);
+        let patch1 = Mismatch::new(&base, &from_str_vec(vec!["a", "c"])).unwrap();
+        let patch2 = Mismatch::new(&base, &from_str_vec(vec!["a", "b", "d"])).unwrap();
+        let c = patch1.conflicts(&patch2);
+        assert_eq!(c.len(), 1, "{:?}", c);
+        assert_eq!(c[0].reason, ConflictReason::ShiftedIndex);
+        assert_eq!(c[0].path, vec![DocIndex::Idx(1)]);
+        assert_eq!(patch1.is_intersect(&patch2).unwrap(), !c.is_empty());
+    }
+
+    #[test]
+    fn test_apply_tx_rollback() {
+        let base = from_json(r#"{"a": "b", "c": {"d": [1, 2, 3]}, "e": "f"}"#).unwrap();
+        let name = |n: &str| DocIndex::Name(n.to_string());
+        let arr = vec![name("c"), name("d")];
+        let patch = Mismatch(vec![
+            Hunk { path: vec![name("a")], value: HunkAction::Update(GenericValue::Null) },
+            Hunk { path: vec![name("e")], value: HunkAction::Swap(name("a")) },
+            Hunk { path: vec![name("x")], value: HunkAction::Insert(GenericValue::Boolean(true)) },
+            Hunk { path: [arr.clone(), vec![DocIndex::Idx(0)]].concat(), value: HunkAction::Remove },
+            Hunk { path: [arr.clone(), vec![DocIndex::Idx(1)]].concat(), value: HunkAction::Clone(DocIndex::Idx(0)) },
+            Hunk { path: [arr.clone(), vec![DocIndex::Idx(0)]].concat(), value: HunkAction::Swap(DocIndex::Idx(2)) },
+            Hunk { path: [arr.clone(), vec![DocIndex::Idx(9)]].concat(), value: HunkAction::Remove },
+        ]);
+        let mut doc = base.clone();
+        assert!(patch.apply_tx(&mut doc).is_err());
+        assert_eq!(doc, base);
+
+        // same patch without the last invalid hunk
+        let patch = Mismatch(patch.0.into_iter().take(6).collect());
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, from_json(r#"{"a": "f", "c": {"d": [3, 2, 2]}, "e": null, "x": true}"#).unwrap());
+    }
+
+    #[test]
+    fn test_invert() {
+        let base = from_json(r#"{"a": "b", "l": ["x", "two", "3", "four", "five", {"k": "v"}], "m": {"n": 1}}"#).unwrap();
+        let input = from_json(r#"{"a": "b", "l": ["3", "x", "two", "four and more", {"k": "w"}, "five", "five"], "o": [true]}"#).unwrap();
+        let patch = Mismatch::new_with(&base, &input, &DiffOptions::default().with_text(TextDiff::new(0, Granularity::Char))).unwrap();
+        let mut doc = base.clone();
+        patch.apply_mut(&mut doc, true).unwrap();
+        assert_eq!(doc, input);
+
+        let inv = patch.invert(&base).unwrap();
+        assert!(inv.0.iter().any(|h| matches!(h.value, HunkAction::UpdateTxt(_))), "{:?}", inv);
+        inv.apply_mut(&mut doc, true).unwrap();
+        assert_eq!(doc, base);
+    }
+
     #[test]
     fn test_intersect_vec2() {
         let base = from_str_vec(vec!["a", "b", "c"]);
@@ -631,5 +1130,75 @@ mod tests {
         assert!(patch2.is_intersect(&patch3).unwrap(), "{:?} vs {:?}", patch2, patch3);
     }
 
+    #[test]
+    fn test_diff_filters() {
+        let base = from_json(r#"{"id": 1, "etag": "a", "meta": {"updated_at": "t1", "by": "x"}, "spec": {"n": 1, "etag": "b"},
+            "items": [{"name": "a", "etag": "c"}, {"name": "b", "etag": "d"}]}"#).unwrap();
+        let input = from_json(r#"{"id": 2, "etag": "e", "meta": {"updated_at": "t2"}, "spec": {"n": 2, "etag": "f", "new": 0},
+            "items": [{"name": "b", "etag": "g"}, {"name": "c", "etag": "h"}]}"#).unwrap();
+        let options = DiffOptions::default().with_exclude("$.meta.*").unwrap().with_exclude("$..etag").unwrap();
+        let patch = Mismatch::new_with(&base, &input, &options).unwrap();
+        assert!(patch.0.iter().all(|h| !h.path.contains(&DocIndex::Name("etag".into())) && h.path[0] != DocIndex::Name("meta".into())), "{:?}", patch);
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, from_json(r#"{"id": 2, "etag": "a", "meta": {"updated_at": "t1", "by": "x"}, "spec": {"n": 2, "etag": "b", "new": 0},
+            "items": [{"name": "b", "etag": "d"}, {"name": "c", "etag": "h"}]}"#).unwrap());
+
+        let options = DiffOptions::default().with_include("$.spec").unwrap().with_include("$.items[*].name").unwrap()
+            .with_exclude("$.spec.new").unwrap();
+        let patch = Mismatch::new_with(&base, &input, &options).unwrap();
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, from_json(r#"{"id": 1, "etag": "a", "meta": {"updated_at": "t1", "by": "x"}, "spec": {"n": 2, "etag": "f"},
+            "items": [{"name": "b", "etag": "d"}, {"name": "c", "etag": "h"}]}"#).unwrap());
+
+        assert!(DiffOptions::default().with_exclude("$..").is_err());
+        assert!(DiffOptions::default().with_exclude("etag").is_err());
+    }
+
+    #[test]
+    fn test_text_diff() {
+        let base = from_json(r#"{"title": "short text", "notes": ["one long line", "other"], "body": "first line\nsecond"}"#).unwrap();
+        let input = from_json(r#"{"title": "short text fixed", "notes": ["one more long line", "other"], "body": "first line\nsecond line"}"#).unwrap();
+        let txt = |p: &Mismatch| p.0.iter().filter(|h| matches!(h.value, HunkAction::UpdateTxt(_))).count();
+        // by default the short strings of array elements only
+        let patch = Mismatch::new(&base, &input).unwrap();
+        assert_eq!(txt(&patch), 1, "{:?}", patch);
+        assert!(patch.0.iter().any(|h| h.path == vec![DocIndex::Name("notes".into()), DocIndex::Idx(0)]
+            && matches!(h.value, HunkAction::UpdateTxt(_))), "{:?}", patch);
+
+        let options = DiffOptions::default().with_text(TextDiff::new(0, Granularity::Word));
+        let patch = Mismatch::new_with(&base, &input, &options).unwrap();
+        assert_eq!(txt(&patch), 3, "{:?}", patch);
+        assert!(patch.0.iter().any(|h| h.value == HunkAction::UpdateTxt(vec![txt::DiffOp::Append { index: 0, pos: 4, value: "more ".into() }])), "{:?}", patch);
+
+        let options = options.with_text_at("$.notes[*]", TextDiff::new(usize::MAX, Granularity::Line)).unwrap();
+        let patch = Mismatch::new_with(&base, &input, &options).unwrap();
+        assert_eq!(txt(&patch), 2, "{:?}", patch);
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, input);
+    }
+
+    #[cfg(feature="preserve_order")]
+    #[test]
+    fn test_key_order() {
+        let yaml = "b: 1\na: 2\nc:\n  y: 3\n  x: 4\n";
+        let base = from_yaml(yaml).unwrap();
+        assert_eq!(to_yaml(&base).unwrap(), yaml);
+        let input = from_yaml("c:\n  y: 3\n  x: 5\nb: 1\nd: 6\n").unwrap();
+        let patch = Mismatch::new(&base, &input).unwrap();
+        assert!(patch.hunks().contains(&Hunk { path: vec![DocIndex::Name("c".into())], value: HunkAction::Move(DocIndex::Idx(0)) }), "{:?}", patch);
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(to_yaml(&doc).unwrap(), to_yaml(&input).unwrap());
+        patch.invert(&base).unwrap().apply_tx(&mut doc).unwrap();
+        assert_eq!(to_yaml(&doc).unwrap(), yaml);
+
+        let other = Mismatch::new(&base, &from_yaml("b: 1\na: 2\nc:\n  y: 3\n  x: 4\ne: 7\n").unwrap()).unwrap();
+        assert!(patch.conflicts(&other).iter().any(|c| c.reason == ConflictReason::KeyOrder), "{:?}", patch.conflicts(&other));
+        let nested = Mismatch::new(&base, &from_yaml("b: 1\na: 2\nc:\n  y: 0\n  x: 4\n").unwrap()).unwrap();
+        assert!(!patch.is_intersect(&nested).unwrap());
+    }
 
 }
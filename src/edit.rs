@@ -0,0 +1,351 @@
+use std::str::FromStr;
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, Table, Value};
+use crate::diff::Mismatch;
+use crate::DocError;
+use crate::generic::{from_toml, from_yaml, DocIndex, GenericValue, Hunk, HunkAction};
+use crate::json_patch::get;
+use crate::yaml_edit::YamlSource;
+
+/// apply the patch to YAML source text, only the nodes on the hunk paths are rewritten,
+/// so the comments, blank lines, anchors and quoting of the rest stay as is;
+/// the source out of the supported subset of block YAML fails with the error, not rewritten as a whole
+pub fn apply_yaml(source: &str, patch: &Mismatch) -> Result<String, DocError> {
+    let mut doc = from_yaml(source).map_err(|e| DocError::new(e.to_string()))?;
+    let mut yaml = YamlSource::new(source)?;
+    for h in &patch.0 {
+        for edit in edits(h, &mut doc)? {
+            yaml.edit(&edit, &doc)?;
+        }
+    }
+    let text = yaml.text();
+    verify(from_yaml(&text).map_err(|e| DocError::new(e.to_string()))?, &doc)?;
+    Ok(text)
+}
+
+/// apply the patch to TOML source text, only the items on the hunk paths are rewritten,
+/// so the comments, blank lines and formatting of the rest stay as is
+pub fn apply_toml(source: &str, patch: &Mismatch) -> Result<String, DocError> {
+    let mut doc = from_toml(source).map_err(|e| DocError::new(e.to_string()))?;
+    let mut toml = DocumentMut::from_str(source).map_err(|e| DocError::new(e.to_string()))?;
+    for h in &patch.0 {
+        for edit in edits(h, &mut doc)? {
+            edit_toml(&mut toml, &edit, &doc)?;
+        }
+    }
+    let text = toml.to_string();
+    verify(from_toml(&text).map_err(|e| DocError::new(e.to_string()))?, &doc)?;
+    Ok(text)
+}
+
+/// the edited source must read as the patched document
+fn verify(edited: GenericValue, doc: &GenericValue) -> Result<(), DocError> {
+    if edited != *doc {
+        return Err(DocError::new("Source edit does not match the patched document"));
+    }
+    Ok(())
+}
+
+/// change of the source node, the value to write is taken from the patched document
+pub(crate) enum Edit {
+    /// set the value, the map key added if absent
+    Set(Vec<DocIndex>),
+    /// remove the map key or array element
+    Remove(Vec<DocIndex>),
+    /// insert the array element
+    Insert(Vec<DocIndex>),
+    /// move the map key to the position
+    Move(Vec<DocIndex>, usize),
+}
+
+/// apply the hunk to the document and return the source changes
+fn edits(h: &Hunk, doc: &mut GenericValue) -> Result<Vec<Edit>, DocError> {
+    if h.apply(doc)?.is_empty() {
+        return Ok(vec![]);
+    }
+    let path = h.path.clone();
+    let other = |i: &DocIndex| {
+        let mut p = path.clone();
+        p.pop();
+        p.push(i.clone());
+        p
+    };
+    Ok(match (path.last(), &h.value) {
+        (None, _) => vec![],
+        (_, HunkAction::Remove) => vec![Edit::Remove(path)],
+        (Some(DocIndex::Idx(_)), HunkAction::Insert(_) | HunkAction::Clone(_)) => vec![Edit::Insert(path)],
+        (Some(DocIndex::Idx(_)), HunkAction::Swap(j)) => {
+            let j = other(j);
+            if j.last() == path.last() || get(doc, &path).is_none() || get(doc, &j).is_none() {
+                vec![] // out of bounds swap is no-op
+            } else {
+                vec![Edit::Set(path), Edit::Set(j)]
+            }
+        }
+        (Some(DocIndex::Name(_)), HunkAction::Swap(k)) => {
+            let k = other(k);
+            if get(doc, &k).is_some() {
+                vec![Edit::Set(path), Edit::Set(k)]
+            } else {
+                vec![Edit::Set(path), Edit::Remove(k)]
+            }
+        }
+        (_, HunkAction::Move(DocIndex::Idx(to))) if cfg!(feature="preserve_order") => vec![Edit::Move(path, *to)],
+        (_, HunkAction::Move(_)) => vec![],
+        _ => vec![Edit::Set(path)],
+    })
+}
+
+fn edit_toml(toml: &mut DocumentMut, edit: &Edit, doc: &GenericValue) -> Result<(), DocError> {
+    let path = match edit {
+        Edit::Set(p) | Edit::Remove(p) | Edit::Insert(p) | Edit::Move(p, _) => p,
+    };
+    let Some((last, parent)) = path.split_last() else {
+        *toml = DocumentMut::from_str(&crate::generic::to_toml(doc).map_err(|e| DocError::new(e.to_string()))?)
+            .map_err(|e| DocError::new(e.to_string()))?;
+        return Ok(());
+    };
+    let node = toml_node(toml.as_item_mut(), parent).ok_or_else(|| DocError::new(format!("Path not found: {:?}", parent)))?;
+    let value = get(doc, path);
+    match (edit, last, value) {
+        (Edit::Remove(_), DocIndex::Name(k), _) => match node {
+            Item::Table(t) => {
+                t.remove(k);
+                if t.is_empty() {
+                    t.set_implicit(false); // keep the header of the empty table
+                }
+            }
+            node => {
+                if let Some(t) = node.as_table_like_mut() {
+                    t.remove(k);
+                }
+            }
+        },
+        (Edit::Remove(_), DocIndex::Idx(i), _) => match node {
+            Item::ArrayOfTables(a) if *i < a.len() && a.len() > 1 => { a.remove(*i); }
+            Item::ArrayOfTables(_) => return edit_toml(toml, &Edit::Set(parent.to_vec()), doc),
+            Item::Value(Value::Array(a)) if *i < a.len() => { a.remove(*i); }
+            _ => {}
+        },
+        (Edit::Move(..), DocIndex::Name(_), _) => {
+            let order: Vec<&String> = match get(doc, parent) {
+                Some(GenericValue::Map(m)) => m.keys().collect(),
+                _ => vec![],
+            };
+            let position = |k: &str| order.iter().position(|o| *o == k).unwrap_or(usize::MAX);
+            match node {
+                Item::Table(t) => t.sort_values_by(|a, _, b, _| position(a.get()).cmp(&position(b.get()))),
+                Item::Value(Value::InlineTable(t)) => t.sort_values_by(|a, _, b, _| position(a.get()).cmp(&position(b.get()))),
+                _ => {}
+            }
+        }
+        (Edit::Set(_), DocIndex::Name(k), Some(v)) => {
+            let table = matches!(node, Item::Table(_));
+            let t = node.as_table_like_mut().ok_or_else(|| DocError::new(format!("Expected table: {}", k)))?;
+            match t.get_mut(k) {
+                Some(item) if !item.is_none() => replace_item(item, v)?,
+                _ => { t.insert(k, new_item(v, table)?); }
+            }
+        }
+        (Edit::Set(_), DocIndex::Idx(i), Some(v)) => match node {
+            Item::ArrayOfTables(a) if matches!(v, GenericValue::Map(_)) => {
+                let t = a.get_mut(*i).ok_or_else(|| DocError::new(format!("Path not found: {}", i)))?;
+                replace_table(t, v)?;
+            }
+            Item::Value(Value::Array(a)) if *i < a.len() => {
+                let new = keep_quotes(a.get(*i), to_value(v)?);
+                a.replace(*i, new);
+            }
+            _ => return edit_toml(toml, &Edit::Set(parent.to_vec()), doc),
+        },
+        (Edit::Insert(_), DocIndex::Idx(i), Some(v)) => match node {
+            Item::ArrayOfTables(a) if *i <= a.len() && matches!(v, GenericValue::Map(_)) => a.insert(*i, new_table(v)?),
+            Item::Value(Value::Array(a)) if *i <= a.len() => a.insert(*i, to_value(v)?),
+            _ => return edit_toml(toml, &Edit::Set(parent.to_vec()), doc),
+        },
+        _ => return edit_toml(toml, &Edit::Set(parent.to_vec()), doc),
+    }
+    Ok(())
+}
+
+/// toml item on the path, no items created on the way
+fn toml_node<'a>(item: &'a mut Item, path: &[DocIndex]) -> Option<&'a mut Item> {
+    path.iter().try_fold(item, |node, p| match p {
+        DocIndex::Name(k) => node.as_table_like_mut()?.get_mut(k),
+        DocIndex::Idx(i) => node.get_mut(*i),
+    })
+}
+
+/// replace the item value, the decoration around it kept
+fn replace_item(item: &mut Item, v: &GenericValue) -> Result<(), DocError> {
+    match item {
+        Item::Value(old) => {
+            let mut new = keep_quotes(Some(old), to_value(v)?);
+            *new.decor_mut() = old.decor().clone();
+            *old = new;
+        }
+        Item::Table(t) if matches!(v, GenericValue::Map(_)) => replace_table(t, v)?,
+        Item::ArrayOfTables(a) if is_tables(v) => {
+            let mut tables = ArrayOfTables::new();
+            if let GenericValue::Array(vs) = v {
+                for (i, v) in vs.iter().enumerate() {
+                    match a.get_mut(i) {
+                        Some(old) => {
+                            replace_table(old, v)?;
+                            tables.push(old.clone());
+                        }
+                        None => tables.push(new_table(v)?),
+                    }
+                }
+            }
+            *a = tables;
+        }
+        _ => *item = new_item(v, true)?,
+    }
+    Ok(())
+}
+
+fn replace_table(t: &mut Table, v: &GenericValue) -> Result<(), DocError> {
+    let mut new = new_table(v)?;
+    new.set_position(t.position());
+    *new.decor_mut() = t.decor().clone();
+    *t = new;
+    Ok(())
+}
+
+/// a single quoted string stays single quoted if possible
+fn keep_quotes(old: Option<&Value>, new: Value) -> Value {
+    match (old, &new) {
+        (Some(Value::String(old)), Value::String(s)) if old.display_repr().starts_with('\'') && !s.value().contains(['\'', '\n']) =>
+            Value::from_str(&format!("'{}'", s.value())).unwrap_or(new),
+        _ => new,
+    }
+}
+
+fn is_tables(v: &GenericValue) -> bool {
+    matches!(v, GenericValue::Array(a) if !a.is_empty() && a.iter().all(|v| matches!(v, GenericValue::Map(_))))
+}
+
+/// maps in the table context written as tables, otherwise inline
+fn new_item(v: &GenericValue, table: bool) -> Result<Item, DocError> {
+    Ok(match v {
+        GenericValue::Map(_) if table => Item::Table(new_table(v)?),
+        GenericValue::Array(a) if table && is_tables(v) => {
+            let mut tables = ArrayOfTables::new();
+            for v in a {
+                tables.push(new_table(v)?);
+            }
+            Item::ArrayOfTables(tables)
+        }
+        _ => Item::Value(to_value(v)?),
+    })
+}
+
+fn new_table(v: &GenericValue) -> Result<Table, DocError> {
+    let mut t = Table::new();
+    if let GenericValue::Map(m) = v {
+        for (k, v) in m {
+            t.insert(k, new_item(v, true)?);
+        }
+    }
+    Ok(t)
+}
+
+fn to_value(v: &GenericValue) -> Result<Value, DocError> {
+    Ok(match v {
+        GenericValue::Numeric(n) => match (n.0.parse::<i64>(), n.0.parse::<f64>()) {
+            (Ok(i), _) => Value::from(i),
+            (_, Ok(f)) => Value::from(f),
+            _ => return Err(DocError::new(format!("TOML number out of range: {}", n.0))),
+        },
+        GenericValue::Boolean(b) => Value::from(*b),
+        GenericValue::StringValue(s) => Value::from(s.as_str()),
+        GenericValue::Array(a) => Value::Array(a.iter().map(to_value).collect::<Result<_, _>>()?),
+        GenericValue::Map(m) => {
+            let mut t = InlineTable::new();
+            for (k, v) in m {
+                t.insert(k, to_value(v)?);
+            }
+            Value::InlineTable(t)
+        }
+        GenericValue::Null => return Err(DocError::new("TOML has no null value")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::Mismatch;
+    use crate::edit::{apply_toml, apply_yaml};
+    use crate::generic::{from_toml, from_yaml};
+    use crate::MismatchDoc;
+
+    #[test]
+    fn test_apply_yaml() {
+        let source = "# service config\n\
+            defaults: &defaults\n  retries: 3\n\n\
+            server:\n  host: 'localhost'   # dev only\n  port: 8080\n  tags:\n    - a\n    - b\n\n\
+            # clients use the defaults\nclient:\n  <<: *defaults\n  name: \"main\"\n";
+        let target = "# service config\n\
+            defaults: &defaults\n  retries: 3\n\n\
+            server:\n  host: 'example.com'   # dev only\n  tags:\n    - a\n    - x\n    - b\n  tls:\n    cert: a.pem\n\n\
+            # clients use the defaults\nclient:\n  <<: *defaults\n  name: \"second\"\n";
+        let base = from_yaml(source).unwrap();
+        let input = from_yaml(target).unwrap();
+        let m = Mismatch::new(&base, &input).unwrap();
+        assert_eq!(apply_yaml(source, &m).unwrap(), target);
+
+        let m = Mismatch::new(&base, &from_yaml(&source.replace("retries: 3", "retries: 4")).unwrap()).unwrap();
+        assert!(apply_yaml(source, &m).is_err(), "anchored node");
+    }
+
+    #[test]
+    fn test_apply_yaml_structure() {
+        let source = "a:\n- 1\n- 2\nb: {x: 1, y: 2}  # flow\nc:\n  - name: n1\n    v: 1\n  - name: n2\n    v: 2\n";
+        for target in [
+            "a:\n- 2\nb: {x: 1, y: 2}  # flow\nc:\n  - name: n1\n    v: 1\n  - name: n2\n    v: 2\n",
+            "a:\n- 1\n- 2\nb: {x: 1, y: 3}  # flow\nc:\n  - name: n1\n    v: 1\n  - name: n2\n    v: 2\n",
+            "a:\n- 1\n- 2\nb: {x: 1, y: 2}  # flow\nc:\n  - name: n2\n    v: 2\n",
+            "a:\n- 1\n- 2\nb: {x: 1, y: 2}  # flow\nc:\n  - name: n1\n    v: 1\n  - name: n2\n    v: 3\n",
+        ] {
+            let m = Mismatch::new(&from_yaml(source).unwrap(), &from_yaml(target).unwrap()).unwrap();
+            let edited = apply_yaml(source, &m).unwrap();
+            assert_eq!(from_yaml(&edited).unwrap(), from_yaml(target).unwrap(), "{}", edited);
+            assert!(edited.contains("# flow"));
+        }
+    }
+
+    #[test]
+    fn test_apply_yaml_unsupported() {
+        let apply = |source: &str, target: &str| {
+            let m = Mismatch::new(&from_yaml(source).unwrap(), &from_yaml(target).unwrap()).unwrap();
+            apply_yaml(source, &m).map_err(|e| e.to_string())
+        };
+        // the changed flow collection is rewritten in the block style, the rest is kept
+        assert_eq!(apply("a: [1, 2]  # flow\nb: 1\n", "a: [1, 3]\nb: 1\n").unwrap(), "a:  # flow\n  - 1\n  - 3\nb: 1\n");
+        assert_eq!(apply("a: |2\n    text\n  more\nb: 1\n", "a: |2\n    text\n  more\nb: 2\n").unwrap(),
+            "a: |2\n    text\n  more\nb: 2\n");
+        assert_eq!(apply("a: |\n  ? text\nb: 1\n", "a: |\n  ? text\nb: 2\n").unwrap(), "a: |\n  ? text\nb: 2\n");
+        assert_eq!(apply("a: |2\n    text\n  more\nb: 1\n", "a: |2\n    text\n  less\nb: 1\n").unwrap_err(),
+            "Unsupported YAML at line 1: block scalar with the indentation indicator");
+        assert!(apply("a: x\nb: 1\n", "a: \"  lead\\nmore\"\nb: 1\n").unwrap_err().contains("indentation indicator"));
+        assert_eq!(apply("? k\n: v\nb: 1\n", "? k\n: v\nb: 2\n").unwrap_err(), "Unsupported YAML at line 1: explicit key");
+        assert_eq!(apply("x:\n  a: 1\n  ? k\n  : v\nb: 1\n", "x:\n  a: 2\n  ? k\n  : v\nb: 1\n").unwrap_err(),
+            "Unsupported YAML at line 3: explicit key");
+        assert_eq!(apply("- ? k\n  : v\n- b\n", "- ? k\n  : v\n- c\n").unwrap_err(), "Unsupported YAML at line 1: explicit key");
+        let m = Mismatch::new(&from_yaml("a: 1\n").unwrap(), &from_yaml("a: 2\n").unwrap()).unwrap();
+        assert!(apply_yaml("a: 1\n---\na: 1\n", &m).unwrap_err().to_string().contains("more than one document"));
+    }
+
+    #[test]
+    fn test_apply_toml() {
+        let source = "# package\n[package]\nname = 'demo'  # the name\nversion = \"0.1.0\"\n\n\
+            [dependencies]\nserde = { version = \"1.0\", features = [\"derive\"] }\nlog = \"0.4\"\n\n\
+            [[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n";
+        let target = "# package\n[package]\nname = 'renamed'  # the name\nversion = \"0.1.0\"\n\n\
+            [dependencies]\nserde = { version = \"1.0\", features = [\"derive\", \"rc\"] }\n\n\
+            [[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"c\"\n";
+        let base = from_toml(source).unwrap();
+        let input = from_toml(target).unwrap();
+        let m = Mismatch::new(&base, &input).unwrap();
+        assert_eq!(apply_toml(source, &m).unwrap(), target);
+    }
+}
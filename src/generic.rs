@@ -1,7 +1,9 @@
+#[cfg(not(feature="preserve_order"))]
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::hash::{DefaultHasher, Hash, Hasher};
 use serde::{Deserialize, Deserializer, Serialize};
+use crate::diff::{DiffOptions, NumericMode};
 use crate::map_processor::min_map_changes;
 use crate::txt::DiffOp;
 use crate::vec_processor::compute_vec_diff;
@@ -14,7 +16,7 @@ pub enum GenericValue {
     Numeric(NumericString),
 
     // A regular JSON object.
-    Map(HashMap<String, GenericValue>),
+    Map(Map),
 
     // A regular JSON array.
     Array(Vec<GenericValue>),
@@ -29,6 +31,36 @@ pub enum GenericValue {
     Null,
 }
 
+/// document object, keeps the keys order with `preserve_order` feature
+#[cfg(not(feature="preserve_order"))]
+pub type Map = HashMap<String, GenericValue>;
+/// document object, keeps the keys order with `preserve_order` feature
+#[cfg(feature="preserve_order")]
+pub type Map = indexmap::IndexMap<String, GenericValue>;
+
+/// remove the key and shift the next keys back, returns the key position and the value
+pub(crate) fn remove_key(m: &mut Map, key: &str) -> Option<(usize, GenericValue)> {
+    #[cfg(feature="preserve_order")]
+    return m.shift_remove_full(key).map(|(i, _, v)| (i, v));
+    #[cfg(not(feature="preserve_order"))]
+    return m.remove(key).map(|v| (0, v));
+}
+
+/// move the key to the position, returns the previous position;
+/// no changes but the key check if the order is not preserved
+pub(crate) fn move_key(m: &mut Map, key: &str, to: usize) -> Option<usize> {
+    #[cfg(feature="preserve_order")]
+    {
+        let from = m.get_index_of(key)?;
+        if to >= m.len() {
+            return None;
+        }
+        m.move_index(from, to);
+        Some(from)
+    }
+    #[cfg(not(feature="preserve_order"))]
+    m.contains_key(key).then_some(to)
+}
 
 
 // Manually implement `Hash` for MyData.
@@ -36,26 +68,95 @@ pub enum GenericValue {
 // To make it deterministic, we sort the key-value pairs before hashing.
 impl Hash for GenericValue {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            GenericValue::Map(map) => {
-                // Collect keys and sort them to ensure a deterministic hash.
-                let mut keys: Vec<&String> = map.keys().collect();
-                keys.sort_unstable();
-
-                // Hash the number of entries and then each sorted key-value pair.
-                map.len().hash(state);
-                for key in keys {
-                    key.hash(state);
-                    map.get(key).unwrap().hash(state);
+        hash_value(self, None, &mut vec![], state);
+    }
+}
+
+/// hash of the value, with the options the numbers hash by the decimal value in the value mode
+/// and the filtered subtrees of the path are skipped
+fn hash_value<H: Hasher>(value: &GenericValue, options: Option<&DiffOptions>, path: &mut Vec<DocIndex>, state: &mut H) {
+    let filters = options.filter(|o| o.has_filters());
+    match value {
+        GenericValue::Map(map) => {
+            // Collect keys and sort them to ensure a deterministic hash.
+            let mut keys: Vec<&String> = map.keys().filter(|k| filters.is_none_or(|o| {
+                path.push(DocIndex::Name((*k).clone()));
+                let filtered = o.is_filtered(path);
+                path.pop();
+                !filtered
+            })).collect();
+            keys.sort_unstable();
+
+            // Hash the number of entries and then each sorted key-value pair.
+            keys.len().hash(state);
+            for key in keys {
+                key.hash(state);
+                if filters.is_some() {
+                    path.push(DocIndex::Name(key.clone()));
+                }
+                hash_value(map.get(key).unwrap(), options, path, state);
+                if filters.is_some() {
+                    path.pop();
+                }
+            }
+        }
+        GenericValue::Array(arr) => {
+            arr.len().hash(state);
+            for (i, v) in arr.iter().enumerate() {
+                if filters.is_some() {
+                    path.push(DocIndex::Idx(i));
+                }
+                hash_value(v, options, path, state);
+                if filters.is_some() {
+                    path.pop();
                 }
             }
-            GenericValue::Array(arr) => arr.hash(state),
-            GenericValue::Numeric(num) => num.hash(state),
-            GenericValue::Boolean(b) => b.hash(state),
-            GenericValue::StringValue(s) => s.hash(state),
-            GenericValue::Null => 0.hash(state), // A simple hash for a null value
         }
+        GenericValue::Numeric(num) => match num.decimal().filter(|_| options.is_some_and(|o| o.numeric() == NumericMode::Value)) {
+            Some(d) => d.hash(state),
+            None => num.hash(state),
+        },
+        GenericValue::Boolean(b) => b.hash(state),
+        GenericValue::StringValue(s) => s.hash(state),
+        GenericValue::Null => 0.hash(state), // A simple hash for a null value
+    }
+}
+
+/// the value equal to the canonical values of the values equal with the options: the numbers
+/// as the decimal value in the value mode and the filtered subtrees of the path removed
+pub(crate) fn canonical(value: &GenericValue, options: &DiffOptions, path: &mut Vec<DocIndex>) -> GenericValue {
+    match value {
+        GenericValue::Map(map) => GenericValue::Map(map.iter().filter_map(|(k, v)| {
+            path.push(DocIndex::Name(k.clone()));
+            let v = (!options.is_filtered(path)).then(|| canonical(v, options, path));
+            path.pop();
+            v.map(|v| (k.clone(), v))
+        }).collect()),
+        GenericValue::Array(arr) => GenericValue::Array(arr.iter().enumerate().map(|(i, v)| {
+            path.push(DocIndex::Idx(i));
+            let v = canonical(v, options, path);
+            path.pop();
+            v
+        }).collect()),
+        GenericValue::Numeric(num) => match num.decimal().filter(|_| options.numeric() == NumericMode::Value) {
+            Some((negative, digits, exp)) =>
+                GenericValue::Numeric(NumericString(format!("{}{}e{}", if negative { "-" } else { "" }, digits, exp))),
+            None => value.clone(),
+        },
+        _ => value.clone(),
+    }
+}
+
+/// hash of the value to match equal values in the diff with the options,
+/// the path of the value is required only if the options have filters
+pub(crate) fn hs_with(input: &GenericValue, options: &DiffOptions, path: impl FnOnce() -> Vec<DocIndex>) -> u64 {
+    if options.numeric() == NumericMode::Text && !options.has_filters() {
+        return hs(input);
     }
+    let mut path = if options.has_filters() { path() } else { vec![] };
+    let mut hasher = DefaultHasher::new();
+    hash_value(input, Some(options), &mut path, &mut hasher);
+    hasher.finish()
 }
 
 pub(crate) fn hs<T: Hash>(input: &T) -> u64 {
@@ -74,7 +175,7 @@ pub enum DocIndex {
     Idx(usize)
 }
 
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 /// value of changes
 pub enum HunkAction {
     /// remove array element or map or document node
@@ -99,6 +200,10 @@ pub enum HunkAction {
     /// DocIndex must match the type of element at path
     /// if applied to array, then elements will shift right
     Clone(DocIndex),
+
+    /// move map key to the DocIndex::Idx position of the keys order, the value is the same
+    /// no changes but the key check without `preserve_order` feature
+    Move(DocIndex),
 }
 
 impl HunkAction {
@@ -108,7 +213,7 @@ impl HunkAction {
 
 }
 
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 /// chunk of changes
 pub struct Hunk {
     /// path to an element to operate with
@@ -148,10 +253,18 @@ impl GenericValue {
 
     /// identify minimum changes
     pub fn diff(base: &Self, input: &Self, path: &Vec<DocIndex>) -> Vec<Hunk> {
+        Self::diff_with(base, input, path, &DiffOptions::default())
+    }
+
+    /// identify minimum changes with options
+    pub fn diff_with(base: &Self, input: &Self, path: &Vec<DocIndex>, options: &DiffOptions) -> Vec<Hunk> {
+        if options.has_filters() && options.is_filtered(path) {
+            return vec![];
+        }
         match base {
             GenericValue::Numeric(a) => {
                 if let GenericValue::Numeric(b) = input {
-                    if a != b {
+                    if !options.numeric_eq(a, b) {
                         input.as_update(path)
                     } else {
                         vec![]
@@ -162,14 +275,14 @@ impl GenericValue {
             }
             GenericValue::Map(a) => {
                 if let GenericValue::Map(b) = input {
-                    min_map_changes(a, b, path)
+                    min_map_changes(a, b, path, options)
                 } else {
                     input.as_update(path)
                 }
             }
             GenericValue::Array(a) => {
                 if let GenericValue::Array(b) = input {
-                    compute_vec_diff(a, b, path)
+                    compute_vec_diff(a, b, path, options)
                 } else {
                     input.as_update(path)
                 }
@@ -188,14 +301,10 @@ impl GenericValue {
             GenericValue::StringValue(a) => {
                 if let GenericValue::StringValue(b) = input {
                     if a != b {
-                        if a.len() + b.len() > (u16::MAX >> 4) as usize {
-                            // use text diff
-                            let ops = crate::txt::diff(a, b);
-                            vec![Hunk { path: path.clone(), value: HunkAction::UpdateTxt(ops) }]
-                        } else {
-                            input.as_update(path)
+                        match options.text(path).diff(a, b) {
+                            Some(ops) => vec![Hunk { path: path.clone(), value: HunkAction::UpdateTxt(ops) }],
+                            None => input.as_update(path),
                         }
-                        // input.as_update(path)
                     } else {
                         vec![]
                     }
@@ -237,6 +346,8 @@ pub fn from_toml(s: &str) -> Result<GenericValue, toml::de::Error> {
     toml::from_str(s)
 }
 
+/// serde-xml-rs mapping, the attributes, mixed content and siblings order are lost,
+/// see `xml::XmlDocument` for the faithful model
 pub fn from_xml(s: &str) -> Result<GenericValue, serde_xml_rs::Error> {
     serde_xml_rs::from_str(s)
 }
@@ -266,6 +377,34 @@ pub fn to_xml(value: &GenericValue) -> Result<String, serde_xml_rs::Error> {
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
 pub struct NumericString(pub String);
 
+impl NumericString {
+    /// sign, significant digits and exponent of the decimal number, so `1.0`, `1` and `1e0` are the same;
+    /// None for the special values as `inf` or `NaN`, and the exponent out of range
+    pub(crate) fn decimal(&self) -> Option<(bool, String, i64)> {
+        let s = self.0.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (mantissa, exp) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+            None => (s, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int.is_empty() && frac.is_empty() || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let digits = format!("{}{}", int, frac);
+        let digits = digits.trim_start_matches('0');
+        let significant = digits.trim_end_matches('0');
+        if significant.is_empty() {
+            return Some((false, String::new(), 0)); // zero of any sign
+        }
+        let exp = exp.checked_sub(frac.len() as i64)?.checked_add((digits.len() - significant.len()) as i64)?;
+        Some((negative, significant.to_string(), exp))
+    }
+}
+
 // Manually implement `Serialize` for NumericString.
 // This is necessary to ensure that numbers are serialized as JSON numbers, not strings.
 impl Serialize for NumericString {
@@ -330,6 +469,32 @@ impl<'de> Deserialize<'de> for NumericString {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diff::Mismatch;
+    use crate::MismatchDoc;
+
+    #[test]
+    fn test_numeric_value() {
+        let n = |s: &str| NumericString(s.to_string()).decimal();
+        assert_eq!(n("1.0"), n("1"));
+        assert_eq!(n("1e2"), n("100"));
+        assert_eq!(n("-0"), n("0.000"));
+        assert_eq!(n("0.1"), n("1E-1"));
+        assert_eq!(n("123456789012345678901234567890.5"), n("1234567890123456789012345678905e-1"));
+        assert_ne!(n("123456789012345678901234567890"), n("123456789012345678901234567891"));
+        assert_ne!(n("-1"), n("1"));
+        assert_eq!(n("NaN"), None);
+
+        let base = from_json(r#"{"a": 1.0, "b": [1e2, 2, -0], "c": 7, "d": [{"id": 1, "v": 2.50}]}"#).unwrap();
+        let input = from_json(r#"{"a": 1, "b": [2.0, 100, 0], "c": 7.5, "d": [{"id": 1.0, "v": 2.5}]}"#).unwrap();
+        assert!(Mismatch::new(&base, &input).unwrap().len() > 1);
+        let options = DiffOptions::default().with_numeric(NumericMode::Value).with_key("$.d[*].id").unwrap();
+        let m = Mismatch::new_with(&base, &input, &options).unwrap();
+        let swap = Hunk { path: vec![DocIndex::Name("b".into()), DocIndex::Idx(0)], value: HunkAction::Swap(DocIndex::Idx(1)) };
+        let c = Hunk { path: vec![DocIndex::Name("c".into())], value: HunkAction::Update(GenericValue::Numeric(NumericString("7.5".into()))) };
+        let mut hunks = m.hunks().to_vec();
+        hunks.sort_by_key(|h| h.to_string());
+        assert_eq!(hunks, vec![swap, c]);
+    }
 
     #[test]
     fn parsing_test_1() {
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use crate::DocError;
+use crate::generic::{DocIndex, GenericValue, Hunk, HunkAction};
+
+/// RFC 6902 JSON Patch operation, where path and from are JSON Pointers (RFC 6901)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Operation {
+    Add { path: String, value: GenericValue },
+    Remove { path: String },
+    Replace { path: String, value: GenericValue },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: GenericValue },
+}
+
+impl Operation {
+    /// target JSON Pointer of the operation
+    pub fn path(&self) -> &str {
+        match self {
+            Operation::Add { path, .. } | Operation::Remove { path } | Operation::Replace { path, .. }
+            | Operation::Move { path, .. } | Operation::Copy { path, .. } | Operation::Test { path, .. } => path,
+        }
+    }
+}
+
+/// convert hunks to operations, each hunk applies to a copy of the base
+/// to resolve text updates, map swaps and skip no-op array swaps and clones
+pub(crate) fn to_operations(hunks: &[Hunk], base: &GenericValue) -> Result<Vec<Operation>, DocError> {
+    let mut doc = base.clone();
+    let mut ops = Vec::with_capacity(hunks.len());
+    for h in hunks {
+        let path = pointer(&h.path);
+        let parent = &h.path[..h.path.len().saturating_sub(1)];
+        let sibling = |i: &DocIndex| {
+            let mut p = parent.to_vec();
+            p.push(i.clone());
+            p
+        };
+        match (h.path.last(), &h.value) {
+            (None, HunkAction::Update(value) | HunkAction::Insert(value)) =>
+                ops.push(Operation::Replace { path, value: value.clone() }),
+            (None, _) => return Err(DocError::new(format!("Unsupported root change: {:?}", h.value))),
+            (_, HunkAction::Remove) => ops.push(Operation::Remove { path }),
+            (Some(DocIndex::Idx(_)), HunkAction::Update(value)) => ops.push(Operation::Replace { path, value: value.clone() }),
+            (Some(DocIndex::Name(_)), HunkAction::Update(value) | HunkAction::Insert(value)) if get(&doc, &h.path).is_some() =>
+                ops.push(Operation::Replace { path, value: value.clone() }),
+            (_, HunkAction::Update(value) | HunkAction::Insert(value)) => ops.push(Operation::Add { path, value: value.clone() }),
+            (_, HunkAction::UpdateTxt(_)) => {} // resolved after apply
+            (_, HunkAction::Move(_)) => {} // object keys are unordered, the key order of `preserve_order` is lost
+            (Some(DocIndex::Idx(i)), HunkAction::Swap(DocIndex::Idx(j))) => {
+                let len = array_len(&doc, parent);
+                if i != j && *i < len && *j < len {
+                    let (a, b) = (*i.min(j), *i.max(j));
+                    // element a goes after b, then b shifted left goes to a
+                    ops.push(Operation::Move { from: pointer(&sibling(&DocIndex::Idx(a))), path: pointer(&sibling(&DocIndex::Idx(b))) });
+                    ops.push(Operation::Move { from: pointer(&sibling(&DocIndex::Idx(b - 1))), path: pointer(&sibling(&DocIndex::Idx(a))) });
+                }
+            }
+            (Some(DocIndex::Name(_)), HunkAction::Swap(k @ DocIndex::Name(_))) => {
+                let other = sibling(k);
+                let value = get(&doc, &other).ok_or_else(|| DocError::new(format!("Path not found: {}", pointer(&other))))?;
+                ops.push(Operation::Add { path, value: value.clone() });
+                ops.push(match get(&doc, &h.path) {
+                    Some(value) => Operation::Add { path: pointer(&other), value: value.clone() },
+                    None => Operation::Remove { path: pointer(&other) },
+                });
+            }
+            (Some(DocIndex::Idx(i)), HunkAction::Clone(DocIndex::Idx(j))) => {
+                if i != j && *j < array_len(&doc, parent) {
+                    ops.push(Operation::Copy { from: pointer(&sibling(&DocIndex::Idx(*j))), path });
+                }
+            }
+            (Some(DocIndex::Name(_)), HunkAction::Clone(k @ DocIndex::Name(_))) =>
+                ops.push(Operation::Copy { from: pointer(&sibling(k)), path }),
+            (_, HunkAction::Swap(v) | HunkAction::Clone(v)) => return Err(DocError::new(format!("index type must match: {:?}", v))),
+        }
+        h.apply(&mut doc)?;
+        if let HunkAction::UpdateTxt(_) = &h.value {
+            let value = get(&doc, &h.path).cloned().unwrap_or(GenericValue::Null);
+            ops.push(Operation::Replace { path: pointer(&h.path), value });
+        }
+    }
+    Ok(ops)
+}
+
+/// convert operations to hunks, each operation applies to a copy of the base to resolve JSON Pointers
+/// to a map key or array index and check the test operations
+pub(crate) fn to_hunks(ops: &[Operation], base: &GenericValue) -> Result<Vec<Hunk>, DocError> {
+    let mut doc = base.clone();
+    let mut hunks = Vec::with_capacity(ops.len());
+    let mut push = |doc: &mut GenericValue, path: Vec<DocIndex>, value: HunkAction| -> Result<(), DocError> {
+        // the hunk of the empty path changes nothing
+        if path.is_empty() {
+            return Err(DocError::new("Root replace has no HunkAction equivalent"));
+        }
+        let h = Hunk { path, value };
+        h.apply(doc)?;
+        hunks.push(h);
+        Ok(())
+    };
+    for op in ops {
+        match op {
+            Operation::Add { path, value } => {
+                let path = resolve(&doc, path, true)?;
+                push(&mut doc, path.clone(), add(&path, value.clone()))?;
+            }
+            Operation::Remove { path } => {
+                let path = resolve(&doc, path, false)?;
+                if path.is_empty() {
+                    return Err(DocError::new("Root can not be removed"));
+                }
+                push(&mut doc, path, HunkAction::Remove)?;
+            }
+            Operation::Replace { path, value } => {
+                let path = resolve(&doc, path, false)?;
+                push(&mut doc, path, HunkAction::Update(value.clone()))?;
+            }
+            Operation::Move { from, path: to } => {
+                let from_path = resolve(&doc, from, false)?;
+                if from == to {
+                    continue;
+                }
+                if to.starts_with(&format!("{}/", from)) {
+                    return Err(DocError::new(format!("Can not move {} to own child {}", from, to)));
+                }
+                let from = from_path;
+                let value = get(&doc, &from).cloned().unwrap_or(GenericValue::Null);
+                push(&mut doc, from, HunkAction::Remove)?;
+                let path = resolve(&doc, to, true)?;
+                push(&mut doc, path.clone(), add(&path, value))?;
+            }
+            Operation::Copy { from, path } => {
+                let from = resolve(&doc, from, false)?;
+                let path = resolve(&doc, path, true)?;
+                let same_parent = !from.is_empty() && !path.is_empty()
+                    && from[..from.len() - 1] == path[..path.len() - 1];
+                let value = match (from.last(), path.last()) {
+                    (Some(src @ DocIndex::Idx(i)), Some(DocIndex::Idx(j))) if same_parent && i != j => HunkAction::Clone(src.clone()),
+                    (Some(src @ DocIndex::Name(_)), Some(DocIndex::Name(_))) if same_parent => HunkAction::Clone(src.clone()),
+                    _ => add(&path, get(&doc, &from).cloned().unwrap_or(GenericValue::Null)),
+                };
+                push(&mut doc, path, value)?;
+            }
+            Operation::Test { path, value } => {
+                let p = resolve(&doc, path, false)?;
+                if get(&doc, &p) != Some(value) {
+                    return Err(DocError::new(format!("Test failed: {}", path)));
+                }
+            }
+        }
+    }
+    Ok(hunks)
+}
+
+/// insert to an array shifts elements right, but set a map key
+fn add(path: &[DocIndex], value: GenericValue) -> HunkAction {
+    match path.last() {
+        Some(DocIndex::Idx(_)) => HunkAction::Insert(value),
+        _ => HunkAction::Update(value),
+    }
+}
+
+/// JSON Pointer from the document path
+pub(crate) fn pointer(path: &[DocIndex]) -> String {
+    path.iter().map(|p| match p {
+        DocIndex::Name(n) => format!("/{}", n.replace('~', "~0").replace('/', "~1")),
+        DocIndex::Idx(i) => format!("/{}", i),
+    }).collect()
+}
+
+/// document path from JSON Pointer, where the document defines array index or map key;
+/// the last element may be absent if the path is to add
+fn resolve(doc: &GenericValue, pointer: &str, to_add: bool) -> Result<Vec<DocIndex>, DocError> {
+    let not_found = || DocError::new(format!("Path not found: {}", pointer));
+    if pointer.is_empty() {
+        return Ok(vec![]);
+    }
+    let tokens: Vec<String> = pointer.strip_prefix('/')
+        .ok_or_else(|| DocError::new(format!("Invalid JSON Pointer: {}", pointer)))?
+        .split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect();
+    let mut path = Vec::with_capacity(tokens.len());
+    let mut node = Some(doc);
+    for (i, token) in tokens.iter().enumerate() {
+        let last = i == tokens.len() - 1;
+        node = match node.ok_or_else(not_found)? {
+            GenericValue::Map(m) => {
+                path.push(DocIndex::Name(token.clone()));
+                if !last || !to_add {
+                    Some(m.get(token).ok_or_else(not_found)?)
+                } else {
+                    None
+                }
+            }
+            GenericValue::Array(a) => {
+                let idx = if token == "-" && last && to_add {
+                    a.len()
+                } else if token == "0" || (!token.starts_with('0') && token.bytes().all(|b| b.is_ascii_digit())) {
+                    token.parse().map_err(|_| not_found())?
+                } else {
+                    return Err(DocError::new(format!("Invalid array index: {}", pointer)));
+                };
+                if idx > a.len() || (idx == a.len() && !(last && to_add)) {
+                    return Err(not_found());
+                }
+                path.push(DocIndex::Idx(idx));
+                a.get(idx)
+            }
+            _ => return Err(not_found()),
+        };
+    }
+    Ok(path)
+}
+
+pub(crate) fn get<'a>(doc: &'a GenericValue, path: &[DocIndex]) -> Option<&'a GenericValue> {
+    path.iter().try_fold(doc, |node, p| match (node, p) {
+        (GenericValue::Map(m), DocIndex::Name(n)) => m.get(n),
+        (GenericValue::Array(a), DocIndex::Idx(i)) => a.get(*i),
+        _ => None,
+    })
+}
+
+fn array_len(doc: &GenericValue, path: &[DocIndex]) -> usize {
+    match get(doc, path) {
+        Some(GenericValue::Array(a)) => a.len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::Mismatch;
+    use crate::generic::{from_json, from_str_vec, GenericValue};
+    use crate::json_patch::Operation;
+    use crate::{MismatchDoc, MismatchDocMut};
+
+    fn roundtrip(base: &GenericValue, input: &GenericValue) -> Vec<Operation> {
+        let patch = Mismatch::new(base, input).unwrap();
+        let ops = patch.to_json_patch(base).unwrap();
+        let imported = Mismatch::from_json_patch(&ops, base).unwrap();
+        let mut doc = base.clone();
+        imported.apply_tx(&mut doc).unwrap();
+        assert_eq!(&doc, input, "\n{:?}\n{:?}\n{:?}", patch, ops, imported);
+        ops
+    }
+
+    #[test]
+    fn test_json_patch_roundtrip() {
+        roundtrip(&from_str_vec(vec!["1", "two", "3", "4", "five"]), &from_str_vec(vec!["1", "3", "five", "two"]));
+        roundtrip(&from_str_vec(vec!["a", "b", "c"]), &from_str_vec(vec!["a", "b", "a", "c"]));
+        let base = from_json(r#"{"a/b": {"c~": [1, 2]}, "d": "x", "e": [{"f": true}]}"#).unwrap();
+        let input = from_json(r#"{"a/b": {"c~": [2, 3]}, "e": [{"f": false}], "g": null}"#).unwrap();
+        let ops = roundtrip(&base, &input);
+        assert!(ops.iter().any(|o| matches!(o, Operation::Remove { path } if path == "/d")), "{:?}", ops);
+        assert!(ops.iter().any(|o| matches!(o, Operation::Replace { path, .. } if path == "/e/0/f")), "{:?}", ops);
+        assert!(ops.iter().any(|o| o.path().starts_with("/a~1b/c~0/")), "{:?}", ops);
+
+        let base = GenericValue::StringValue("a\n".repeat(3000));
+        let input = GenericValue::StringValue("a\n".repeat(2999) + "b\n");
+        let ops = roundtrip(&from_json(&format!(r#"{{"t": {}}}"#, serde_json::to_string(&base).unwrap())).unwrap(),
+                            &from_json(&format!(r#"{{"t": {}}}"#, serde_json::to_string(&input).unwrap())).unwrap());
+        assert_eq!(ops, vec![Operation::Replace { path: "/t".into(), value: input }]);
+    }
+
+    #[test]
+    fn test_json_patch_import() {
+        let base = from_json(r#"{"a": [1, 2, 3], "b": {"c": "d"}}"#).unwrap();
+        let ops: Vec<Operation> = serde_json::from_str(r#"[
+            {"op": "test", "path": "/b/c", "value": "d"},
+            {"op": "add", "path": "/a/-", "value": 4},
+            {"op": "move", "from": "/a/0", "path": "/a/3"},
+            {"op": "copy", "from": "/b", "path": "/e"},
+            {"op": "replace", "path": "/b/c", "value": "f"}
+        ]"#).unwrap();
+        let patch = Mismatch::from_json_patch(&ops, &base).unwrap();
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, from_json(r#"{"a": [2, 3, 4, 1], "b": {"c": "f"}, "e": {"c": "d"}}"#).unwrap());
+
+        let ops: Vec<Operation> = serde_json::from_str(r#"[{"op": "test", "path": "/b/c", "value": "x"}]"#).unwrap();
+        assert!(Mismatch::from_json_patch(&ops, &base).is_err());
+        let ops: Vec<Operation> = serde_json::from_str(r#"[{"op": "add", "path": "/a/01", "value": 0}]"#).unwrap();
+        assert!(Mismatch::from_json_patch(&ops, &base).is_err());
+        let ops: Vec<Operation> = serde_json::from_str(r#"[{"op": "move", "from": "/x", "path": "/x"}]"#).unwrap();
+        assert!(Mismatch::from_json_patch(&ops, &base).is_err());
+        let ops: Vec<Operation> = serde_json::from_str(r#"[{"op": "move", "from": "/b", "path": "/b"}]"#).unwrap();
+        assert!(Mismatch::from_json_patch(&ops, &base).unwrap().0.is_empty());
+        for op in [r#"{"op": "add", "path": "", "value": 1}"#, r#"{"op": "replace", "path": "", "value": {}}"#,
+            r#"{"op": "copy", "from": "/b", "path": ""}"#] {
+            let ops: Vec<Operation> = serde_json::from_str(&format!("[{}]", op)).unwrap();
+            let err = Mismatch::from_json_patch(&ops, &base).unwrap_err();
+            assert_eq!(err.to_string(), "Root replace has no HunkAction equivalent");
+        }
+        assert!(serde_json::from_str::<Vec<Operation>>(r#"[{"op": "merge", "path": "/a"}]"#).is_err());
+    }
+}
@@ -1,9 +1,19 @@
 #[cfg(feature="patch")] pub mod patch;
+#[cfg(feature="proto")] pub mod proto;
 pub mod txt;
 pub mod diff;
 pub mod generic;
+pub mod merge;
+pub mod json_patch;
+pub mod merge_patch;
+pub mod edit;
+pub mod xml;
 mod vec_processor;
 mod map_processor;
+mod matching;
+mod compose;
+mod rebase;
+mod yaml_edit;
 
 use std::borrow::Cow;
 use std::fmt;
@@ -26,6 +36,19 @@ pub trait MismatchDocMut<T> {
     /// otherwise try to apply all and collect errors on Ok response
     /// - non-transactional behavioral, partial updates possible either on fail_fast!
     fn apply_mut(&self, input: &mut T, fail_fast: bool) -> Result<Vec<DocError>, DocError>;
+
+    /// transactional behavioral: apply all changes or stop on first error
+    /// and restore the document to the exact prior state;
+    /// by default applies to the copy of the document and keeps it on success
+    fn apply_tx(&self, input: &mut T) -> Result<(), DocError>
+    where
+        T: Clone
+    {
+        let mut work = input.clone();
+        self.apply_mut(&mut work, true)?;
+        *input = work;
+        Ok(())
+    }
 }
 
 /// document update Copy on Write
@@ -78,4 +101,32 @@ mod tests {
     fn test_compile() {
         assert!(true);
     }
+
+    /// the implementor of `apply_mut` only gets the transactional apply
+    #[test]
+    fn test_default_apply_tx() {
+        use super::{DocError, MismatchDocMut};
+        struct Push(Vec<i32>);
+        impl MismatchDocMut<Vec<i32>> for Push {
+            fn apply_mut(&self, input: &mut Vec<i32>, fail_fast: bool) -> Result<Vec<DocError>, DocError> {
+                let mut err = Vec::new();
+                for v in &self.0 {
+                    if *v < 0 {
+                        if fail_fast {
+                            return Err(DocError::new("negative"));
+                        }
+                        err.push(DocError::new("negative"));
+                    } else {
+                        input.push(*v);
+                    }
+                }
+                Ok(err)
+            }
+        }
+        let mut doc = vec![1];
+        Push(vec![2, 3]).apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, vec![1, 2, 3]);
+        assert!(Push(vec![4, -1, 5]).apply_tx(&mut doc).is_err());
+        assert_eq!(doc, vec![1, 2, 3]);
+    }
 }
\ No newline at end of file
@@ -1,22 +1,199 @@
-// use diff_doc::*;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::ExitCode;
+use diff_doc::*;
+use diff_doc::generic::*;
 
 /**
 usage examples
 
+diff-doc diff base.json new.json > patch.json
+diff-doc apply base.json patch.json
+diff-doc check patch_a.json patch_b.json
+diff-doc merge base.yaml a.yaml b.yaml
+
+exit code: 0 on success or disjoint patches, 1 on intersect patches, 2 on error
 */
+const USAGE: &str = "usage: diff-doc <command> FILES
+  diff BASE NEW          print patch of BASE to NEW changes
+  apply BASE PATCH       print BASE with PATCH applied
+  check PATCH_A PATCH_B  exit with 1 if patches intersect
+  merge BASE A B         print BASE with changes of A and B, exit with 1 on conflicts
+document format by file extension: .json .yaml .yml .toml .xml, otherwise plain text";
+
+/// document format by the file extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Xml,
+    Text,
+}
+
+impl Format {
+    fn of(file: &str) -> Self {
+        match Path::new(file).extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("json") => Format::Json,
+            Some("yaml") | Some("yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            Some("xml") => Format::Xml,
+            _ => Format::Text,
+        }
+    }
+
+    fn write(self, value: &GenericValue) -> Result<String, Box<dyn Error>> {
+        Ok(match self {
+            Format::Json => to_json(value)?,
+            Format::Yaml => to_yaml(value)?,
+            Format::Toml => to_toml(value)?,
+            Format::Xml => xml::to_string(value)?,
+            Format::Text => return Err("text is not a structured document".into()),
+        })
+    }
+}
 
-fn main() {
-    // let m = Mismatch::new( &json!({"a":["b","c"], "d":["e","f"]}),
-    //                           &json!({"a":["b","c"], "d":["e","g"]})).unwrap();
-    // println!("{}", m);
+enum Doc {
+    Value(GenericValue, Format),
+    Text(String),
+}
 
+fn read_doc(file: &str) -> Result<Doc, Box<dyn Error>> {
+    let s = read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    let format = Format::of(file);
+    let parsed = match format {
+        Format::Json => from_json(&s).map_err(|e| e.to_string()),
+        Format::Yaml => from_yaml(&s).map_err(|e| e.to_string()),
+        Format::Toml => from_toml(&s).map_err(|e| e.to_string()),
+        Format::Xml => xml::from_str(&s).map_err(|e| e.to_string()),
+        Format::Text => return Ok(Doc::Text(s)),
+    };
+    Ok(Doc::Value(parsed.map_err(|e| format!("{}: {}", file, e))?, format))
+}
+
+fn read_patch(file: &str) -> Result<Mismatches, Box<dyn Error>> {
+    let s = read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    Ok(serde_json::from_str(&s).map_err(|e| format!("{}: {}", file, e))?)
+}
+
+fn diff(base: &str, new: &str) -> Result<ExitCode, Box<dyn Error>> {
+    let patch = match (read_doc(base)?, read_doc(new)?) {
+        (Doc::Value(base, _), Doc::Value(new, _)) => Mismatches::Doc(diff::Mismatch::new(&base, &new)?),
+        (Doc::Text(base), Doc::Text(new)) => Mismatches::Text(txt::Mismatch::new(&base, &new)?),
+        _ => return Err("can not compare structured document with plain text".into()),
+    };
+    println!("{}", patch);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn apply(base: &str, patch: &str) -> Result<ExitCode, Box<dyn Error>> {
+    match (read_doc(base)?, read_patch(patch)?) {
+        (Doc::Value(mut doc, format), Mismatches::Doc(m)) => {
+            let edited = match format {
+                Format::Yaml => Some(edit::apply_yaml(&read_to_string(base)?, &m)),
+                Format::Toml => Some(edit::apply_toml(&read_to_string(base)?, &m)),
+                _ => None,
+            };
+            match edited {
+                Some(Ok(source)) => print!("{}", source),
+                other => {
+                    if let Some(Err(e)) = other {
+                        eprintln!("{}, the formatting is not kept", e);
+                    }
+                    m.apply_tx(&mut doc)?;
+                    println!("{}", format.write(&doc)?);
+                }
+            }
+        }
+        (Doc::Text(mut doc), Mismatches::Text(m)) => {
+            m.apply_tx(&mut doc)?;
+            print!("{}", doc);
+        }
+        #[cfg(feature="patch")]
+        (Doc::Text(doc), Mismatches::Patch(m)) => print!("{}", m.apply(&doc)?),
+        _ => return Err("patch type does not match the document".into()),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(a: &str, b: &str) -> Result<ExitCode, Box<dyn Error>> {
+    let intersect = match (read_patch(a)?, read_patch(b)?) {
+        (Mismatches::Doc(a), Mismatches::Doc(b)) => {
+            let conflicts = a.conflicts(&b);
+            for c in &conflicts {
+                println!("{}", serde_json::to_string(c)?);
+            }
+            !conflicts.is_empty()
+        }
+        (Mismatches::Text(a), Mismatches::Text(b)) => a.is_intersect(&b)?,
+        #[cfg(feature="patch")]
+        (Mismatches::Patch(a), Mismatches::Patch(b)) => a.is_intersect(&b)?,
+        _ => return Err("patch types does not match".into()),
+    };
+    Ok(if intersect { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+}
+
+fn merge(base: &str, a: &str, b: &str) -> Result<ExitCode, Box<dyn Error>> {
+    let merged = match (read_doc(base)?, read_doc(a)?, read_doc(b)?) {
+        (Doc::Value(base, format), Doc::Value(a, _), Doc::Value(b, _)) => {
+            let outcome = merge::merge3(&base, &a, &b)?;
+            for c in &outcome.conflicts {
+                eprintln!("{}", serde_json::to_string(c)?);
+            }
+            println!("{}", format.write(&outcome.merged)?);
+            outcome.is_merged()
+        }
+        (Doc::Text(base), Doc::Text(a), Doc::Text(b)) => {
+            let pa = txt::Mismatch::new(&base, &a)?;
+            let pb = txt::Mismatch::new(&base, &b)?;
+            // the changes within the same lines shift by the other changes
+            let pb = if pa.is_intersect(&pb)? { pb.rebase(&pa).ok() } else { Some(pb) };
+            let mut doc = base;
+            pa.apply_tx(&mut doc)?;
+            if let Some(pb) = &pb {
+                pb.apply_tx(&mut doc)?;
+            }
+            print!("{}", doc);
+            pb.is_some()
+        }
+        _ => return Err("can not merge structured document with plain text".into()),
+    };
+    Ok(if merged { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+fn run(args: &[String]) -> Result<ExitCode, Box<dyn Error>> {
+    let files: Vec<&str> = args.iter().skip(1).map(|a| a.as_str()).collect();
+    match (args.first().map(|a| a.as_str()), files.as_slice()) {
+        (Some("diff"), [base, new]) => diff(base, new),
+        (Some("apply"), [base, patch]) => apply(base, patch),
+        (Some("check"), [a, b]) => check(a, b),
+        (Some("merge"), [base, a, b]) => merge(base, a, b),
+        _ => {
+            eprintln!("{}", USAGE);
+            Ok(ExitCode::from(2))
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    run(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        ExitCode::from(2)
+    })
 }
 
 
 #[cfg(test)]
 mod tests {
+    use crate::Format;
+
     #[test]
-    fn test_compile() {
-        assert!(true);
+    fn test_format() {
+        assert_eq!(Format::of("a/b.JSON"), Format::Json);
+        assert_eq!(Format::of("b.yml"), Format::Yaml);
+        assert_eq!(Format::of("b.txt"), Format::Text);
+        assert_eq!(Format::of("Makefile"), Format::Text);
     }
-}
\ No newline at end of file
+}
@@ -1,32 +1,97 @@
-use std::collections::{HashMap, HashSet};
+use crate::diff::DiffOptions;
 use crate::generic::*;
 
 /// identify min_map_changes
-pub(crate) fn min_map_changes(base_map: &HashMap<String, GenericValue>, input_map: &HashMap<String, GenericValue>, path: &Vec<DocIndex>) -> Vec<Hunk> {
+pub(crate) fn min_map_changes(base_map: &Map, input_map: &Map, path: &Vec<DocIndex>, options: &DiffOptions) -> Vec<Hunk> {
         let mut diff = Vec::new();
-        let mut input: HashSet<&String>  = input_map.keys().collect();
 
         for (base_key, base_value) in base_map {
-            input.remove(base_key);
             match input_map.get(base_key) {
                 None => {
+                    if options.has_filters() && options.is_filtered(&key_path(path, base_key)) {
+                        continue;
+                    }
                     Hunk::append(&mut diff, path, DocIndex::Name(base_key.clone()), HunkAction::Remove);
                 }
                 Some(b) => {
                     let mut p = path.clone();
                     p.push(DocIndex::Name(base_key.clone()));
-                    let mut v = GenericValue::diff(base_value, b, &p);
+                    let mut v = GenericValue::diff_with(base_value, b, &p, options);
                     diff.append(&mut v);
                 }
             }
         }
 
-        for key in input {
-            if let Some(value) = input_map.get(key) {
-                Hunk::append(&mut diff, path, DocIndex::Name(key.clone()), HunkAction::Update(value.clone()));
+        for (key, value) in input_map {
+            if base_map.contains_key(key) || (options.has_filters() && options.is_filtered(&key_path(path, key))) {
+                continue;
             }
+            Hunk::append(&mut diff, path, DocIndex::Name(key.clone()), HunkAction::Update(value.clone()));
+        }
+
+        // the order of the map with filtered keys stays as is
+        #[cfg(feature="preserve_order")]
+        if !(options.has_filters() && base_map.keys().chain(input_map.keys()).any(|k| options.is_filtered(&key_path(path, k)))) {
+            diff.append(&mut key_moves(base_map, input_map, path));
         }
 
         diff
     }
 
+fn key_path(path: &[DocIndex], key: &str) -> Vec<DocIndex> {
+    let mut p = path.to_vec();
+    p.push(DocIndex::Name(key.to_string()));
+    p
+}
+
+/// moves of the keys to get the input order after remove and append of the keys,
+/// the longest sequence of keys in the same order stays, the rest moves after the preceding key of input
+#[cfg(feature="preserve_order")]
+fn key_moves(base_map: &Map, input_map: &Map, path: &Vec<DocIndex>) -> Vec<Hunk> {
+    // removed keys are gone and new keys appended
+    let mut order: Vec<&String> = base_map.keys().filter(|k| input_map.contains_key(*k))
+        .chain(input_map.keys().filter(|k| !base_map.contains_key(*k)))
+        .collect();
+    let target: Vec<&String> = input_map.keys().collect();
+    if order == target {
+        return vec![];
+    }
+    let positions: Vec<usize> = target.iter().map(|k| order.iter().position(|o| o == k).unwrap_or(0)).collect();
+    let stay = longest_increasing(&positions);
+
+    let mut moves = Vec::new();
+    for (t, key) in target.iter().enumerate() {
+        if stay[t] {
+            continue;
+        }
+        let from = order.iter().position(|o| o == key).unwrap_or(0);
+        order.remove(from);
+        let to = if t == 0 { 0 } else { order.iter().position(|o| *o == target[t - 1]).map(|p| p + 1).unwrap_or(0) };
+        order.insert(to, key);
+        Hunk::append(&mut moves, path, DocIndex::Name((*key).clone()), HunkAction::Move(DocIndex::Idx(to)));
+    }
+    moves
+}
+
+/// flags of the elements in the longest strictly increasing subsequence
+#[cfg(feature="preserve_order")]
+fn longest_increasing(values: &[usize]) -> Vec<bool> {
+    let mut tails: Vec<usize> = Vec::new(); // index of the smallest tail value of the subsequence by length
+    let mut prev: Vec<Option<usize>> = vec![None; values.len()];
+    for (i, v) in values.iter().enumerate() {
+        let len = tails.partition_point(|t| values[*t] < *v);
+        prev[i] = if len > 0 { Some(tails[len - 1]) } else { None };
+        if len == tails.len() {
+            tails.push(i);
+        } else {
+            tails[len] = i;
+        }
+    }
+    let mut stay = vec![false; values.len()];
+    let mut i = tails.last().copied();
+    while let Some(x) = i {
+        stay[x] = true;
+        i = prev[x];
+    }
+    stay
+}
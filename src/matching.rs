@@ -0,0 +1,184 @@
+/// Myers diff with divide and conquer on the middle snake,
+/// the matched pairs (indices into a and b) of the longest common subsequence in forward order
+pub(crate) fn myers(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    myers_rec(a, 0, b, 0, &mut out);
+    out
+}
+
+fn myers_rec(a: &[&str], ao: usize, b: &[&str], bo: usize, out: &mut Vec<(usize, usize)>) {
+    let (pre, a, b) = strip_prefix(a, b);
+    out.extend((0..pre).map(|i| (ao + i, bo + i)));
+    let (ao, bo) = (ao + pre, bo + pre);
+    let (suf, a, b) = strip_suffix(a, b);
+    if !a.is_empty() && !b.is_empty() {
+        let (x, y, u, v) = middle_snake(a, b);
+        myers_rec(&a[..x], ao, &b[..y], bo, out);
+        out.extend((0..u - x).map(|i| (ao + x + i, bo + y + i)));
+        myers_rec(&a[u..], ao + u, &b[v..], bo + v, out);
+    }
+    out.extend((0..suf).map(|i| (ao + a.len() + i, bo + b.len() + i)));
+}
+
+fn strip_prefix<'a, 'b>(a: &'a [&'b str], b: &'a [&'b str]) -> (usize, &'a [&'b str], &'a [&'b str]) {
+    let n = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    (n, &a[n..], &b[n..])
+}
+
+fn strip_suffix<'a, 'b>(a: &'a [&'b str], b: &'a [&'b str]) -> (usize, &'a [&'b str], &'a [&'b str]) {
+    let n = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+    (n, &a[..a.len() - n], &b[..b.len() - n])
+}
+
+/// the snake (x, y) to (u, v) in the middle of the shortest edit path, both sequences are not empty
+/// and differ in the first and the last elements, so the snake splits the path to two shorter ones
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize, usize, usize) {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let delta = n - m;
+    let odd = delta & 1 != 0;
+    let max = (n + m + 1) / 2 + 1;
+    // furthest x on the diagonal k = x - y, forward and of the reversed sequences
+    let mut vf = vec![0isize; 2 * max as usize + 1];
+    let mut vb = vec![0isize; 2 * max as usize + 1];
+    let at = |k: isize| (k + max) as usize;
+    for d in 0..max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && vf[at(k - 1)] < vf[at(k + 1)]) { vf[at(k + 1)] } else { vf[at(k - 1)] + 1 };
+            let mut y = x - k;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            vf[at(k)] = x;
+            let kr = delta - k;
+            if odd && kr > -d && kr < d && x + vb[at(kr)] >= n {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+        for kr in (-d..=d).step_by(2) {
+            let mut x = if kr == -d || (kr != d && vb[at(kr - 1)] < vb[at(kr + 1)]) { vb[at(kr + 1)] } else { vb[at(kr - 1)] + 1 };
+            let mut y = x - kr;
+            let (x0, y0) = (x, y);
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            vb[at(kr)] = x;
+            let k = delta - kr;
+            if !odd && k >= -d && k <= d && x + vf[at(k)] >= n {
+                return ((n - x) as usize, (m - y) as usize, (n - x0) as usize, (m - y0) as usize);
+            }
+        }
+    }
+    unreachable!("the edit path is not longer than n + m")
+}
+
+/// patience diff: the lines unique in both sequences match by the longest increasing subsequence,
+/// the gaps between them match recursively, or by Myers diff if no unique lines
+pub(crate) fn patience(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let mut out = Vec::new();
+    patience_rec(a, 0, b, 0, &mut out);
+    out
+}
+
+fn patience_rec(a: &[&str], ao: usize, b: &[&str], bo: usize, out: &mut Vec<(usize, usize)>) {
+    let (pre, a, b) = strip_prefix(a, b);
+    out.extend((0..pre).map(|i| (ao + i, bo + i)));
+    let (ao, bo) = (ao + pre, bo + pre);
+    let (suf, a, b) = strip_suffix(a, b);
+    let anchors = unique_anchors(a, b);
+    if anchors.is_empty() {
+        out.extend(myers(a, b).into_iter().map(|(i, j)| (ao + i, bo + j)));
+    } else {
+        let (mut ai, mut bi) = (0, 0);
+        for (i, j) in anchors {
+            patience_rec(&a[ai..i], ao + ai, &b[bi..j], bo + bi, out);
+            out.push((ao + i, bo + j));
+            ai = i + 1;
+            bi = j + 1;
+        }
+        patience_rec(&a[ai..], ao + ai, &b[bi..], bo + bi, out);
+    }
+    out.extend((0..suf).map(|i| (ao + a.len() + i, bo + b.len() + i)));
+}
+
+/// pairs of the lines occurred once in both sequences, the longest sequence in the same order
+fn unique_anchors(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    use std::collections::HashMap;
+    let mut count: HashMap<&str, (usize, usize, usize)> = HashMap::new();
+    for (i, line) in a.iter().enumerate() {
+        let e = count.entry(line).or_insert((0, 0, 0));
+        e.0 += 1;
+        e.2 = i;
+    }
+    let mut pairs = Vec::new();
+    for line in b {
+        if let Some(e) = count.get_mut(line) {
+            e.1 += 1;
+        }
+    }
+    for (j, line) in b.iter().enumerate() {
+        if let Some((1, 1, i)) = count.get(line) {
+            pairs.push((*i, j));
+        }
+    }
+    pairs.sort_unstable();
+    // longest increasing subsequence by the index in b
+    let mut tails: Vec<usize> = Vec::new();
+    let mut prev: Vec<Option<usize>> = vec![None; pairs.len()];
+    for (p, (_, j)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|t| pairs[*t].1 < *j);
+        prev[p] = if len > 0 { Some(tails[len - 1]) } else { None };
+        if len == tails.len() {
+            tails.push(p);
+        } else {
+            tails[len] = p;
+        }
+    }
+    let mut out = Vec::with_capacity(tails.len());
+    let mut p = tails.last().copied();
+    while let Some(x) = p {
+        out.push(pairs[x]);
+        p = prev[x];
+    }
+    out.reverse();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::txt::{compute_diff_with, apply_diff, Algorithm, Granularity};
+
+    #[test]
+    fn test_myers_patience() {
+        let a = vec!["a", "b", "c", "a", "b", "b", "a"];
+        let b = vec!["c", "b", "a", "b", "a", "c"];
+        let m = myers(&a, &b);
+        assert_eq!(m.len(), 4);
+        assert!(m.iter().all(|(i, j)| a[*i] == b[*j]));
+        assert!(m.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1), "{:?}", m);
+
+        let a = vec!["fn a() {", "}", "", "fn b() {", "}"];
+        let b = vec!["fn b() {", "}", "", "fn a() {", "}"];
+        assert_eq!(patience(&a, &b), vec![(1, 1), (2, 2), (4, 4)]);
+    }
+
+    #[test]
+    fn test_large_text() {
+        let old: Vec<String> = (0..50000).map(|i| format!("line {}", i % 9000)).collect();
+        let mut new = old.clone();
+        for i in 0..500 {
+            new.insert(i * 97, format!("new {}", i));
+            new.remove(i * 89 + 1);
+        }
+        let old: Vec<&str> = old.iter().map(|s| s.as_str()).collect();
+        let new: Vec<&str> = new.iter().map(|s| s.as_str()).collect();
+        for algorithm in [Algorithm::Myers, Algorithm::Patience] {
+            let ops = compute_diff_with(&old, &new, Granularity::Line, algorithm);
+            assert_eq!(apply_diff(&old, &ops).unwrap(), new);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::diff::{Conflict, DiffOptions, Mismatch};
+use crate::generic::{GenericValue, HunkAction};
+use crate::{DocError, MismatchDocMut};
+
+/// result of three-way merge: Base vs Document_A vs Document_B
+#[derive(Debug, PartialEq)]
+pub struct MergeOutcome {
+    /// Base + patch_A + patch_B if no conflicts present,
+    /// otherwise Base + patch_A only as patch_B is rejected
+    pub merged: GenericValue,
+    /// Base to Document_A changes
+    pub patch_a: Mismatch,
+    /// Base to Document_B changes
+    pub patch_b: Mismatch,
+    /// hunks of patch_a and patch_b which prevent to apply patches in any order
+    pub conflicts: Vec<Conflict>,
+}
+
+impl MergeOutcome {
+    /// true if both patches applied
+    pub fn is_merged(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// calculate both patches against the base and apply them if disjoint,
+/// the result is the same as apply in any order: Base + patch_A + patch_B = Base + patch_B + patch_A;
+/// the text changes of the same string at different parts of the line apply as patch_B rebased onto patch_A
+pub fn merge3(base: &GenericValue, a: &GenericValue, b: &GenericValue) -> Result<MergeOutcome, DocError> {
+    merge3_with(base, a, b, &DiffOptions::default())
+}
+
+fn is_txt(patch: &Mismatch, hunk: usize) -> bool {
+    matches!(patch.hunks()[hunk].value, HunkAction::UpdateTxt(_))
+}
+
+/// three-way merge with the diff options of both patches
+pub fn merge3_with(base: &GenericValue, a: &GenericValue, b: &GenericValue, options: &DiffOptions) -> Result<MergeOutcome, DocError> {
+    let patch_a = Mismatch::new_with(base, a, options)?;
+    let patch_b = Mismatch::new_with(base, b, options)?;
+    let mut conflicts = patch_a.conflicts(&patch_b);
+
+    let mut merged = base.clone();
+    patch_a.apply_mut(&mut merged, true)?;
+    if conflicts.is_empty() {
+        patch_b.apply_mut(&mut merged, true)?;
+    } else if conflicts.iter().all(|c| is_txt(&patch_a, c.hunk_a) && is_txt(&patch_b, c.hunk_b))
+        && let Ok(rebased) = patch_b.rebase(&patch_a) {
+        // the changes within the same lines of a string shift by the other changes
+        let mut doc = merged.clone();
+        if rebased.apply_tx(&mut doc).is_ok() {
+            merged = doc;
+            conflicts.clear();
+        }
+    }
+
+    Ok(MergeOutcome { merged, patch_a, patch_b, conflicts })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generic::{from_str_vec, from_str_vec2};
+    use super::*;
+    use crate::MismatchDoc;
+    use crate::diff::TextDiff;
+    use crate::txt::Granularity;
+
+    #[test]
+    fn test_merge3_map() {
+        let base = from_str_vec2(vec![("a", "b"), ("c", "d")]);
+        let a = from_str_vec2(vec![("a", "b1"), ("c", "d")]);
+        let b = from_str_vec2(vec![("a", "b"), ("c", "d2")]);
+        let m = merge3(&base, &a, &b).unwrap();
+        assert!(m.is_merged(), "{:?}", m);
+        assert_eq!(m.merged, from_str_vec2(vec![("a", "b1"), ("c", "d2")]));
+        assert_eq!(m.patch_a.len(), 1);
+        assert_eq!(m.patch_b.len(), 1);
+    }
+
+    #[test]
+    fn test_merge3_conflict() {
+        let base = from_str_vec(vec!["a", "b", "c"]);
+        let a = from_str_vec(vec!["a", "b", "d"]);
+        let b = from_str_vec(vec!["a", "f", "e"]);
+        let m = merge3(&base, &a, &b).unwrap();
+        assert!(!m.is_merged());
+        assert!(m.conflicts.iter().any(|c| c.hunk_a == 0 && c.hunk_b == 1), "{:?}", m.conflicts);
+        assert_eq!(m.merged, a);
+    }
+
+    #[test]
+    fn test_merge3_typos() {
+        let base = from_str_vec2(vec![("text", "Teh quick brown fox jumps ovr the lazy dog")]);
+        let a = from_str_vec2(vec![("text", "The very quick brown fox jumps ovr the lazy dog")]);
+        let b = from_str_vec2(vec![("text", "Teh quick brown fox jumps over the lazy dog")]);
+        let options = DiffOptions::default().with_text(TextDiff::new(0, Granularity::Word));
+        let m = merge3_with(&base, &a, &b, &options).unwrap();
+        assert!(m.is_merged(), "{:?}", m);
+        assert_eq!(m.merged, from_str_vec2(vec![("text", "The very quick brown fox jumps over the lazy dog")]));
+        assert!(!merge3(&base, &a, &b).unwrap().is_merged());
+    }
+}
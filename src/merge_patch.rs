@@ -0,0 +1,160 @@
+use crate::diff::Mismatch;
+use crate::DocError;
+use crate::generic::{remove_key, DocIndex, GenericValue, Hunk, HunkAction, Map};
+
+/// apply RFC 7396 JSON Merge Patch to the document and record the changes as a patch,
+/// so it can check for intersection with other patches;
+/// error if the document or the patch is not an object, the root replace has no hunk
+pub fn apply_merge_patch(doc: &mut GenericValue, patch: &GenericValue) -> Result<Mismatch, DocError> {
+    if !matches!((&*doc, patch), (GenericValue::Map(_), GenericValue::Map(_))) {
+        return Err(DocError::new("Merge patch replacing the root has no hunk, the document and the patch must be objects"));
+    }
+    let mut hunks = Vec::new();
+    merge(doc, patch, &mut vec![], &mut hunks);
+    Ok(Mismatch(hunks))
+}
+
+fn merge(doc: &mut GenericValue, patch: &GenericValue, path: &mut Vec<DocIndex>, hunks: &mut Vec<Hunk>) {
+    match (doc, patch) {
+        (GenericValue::Map(m), GenericValue::Map(p)) => {
+            let mut keys: Vec<&String> = p.keys().collect();
+            if !cfg!(feature="preserve_order") {
+                keys.sort_unstable(); // deterministic hunks order, unless the patch order preserved
+            }
+            for k in keys {
+                path.push(DocIndex::Name(k.clone()));
+                match (m.get_mut(k), &p[k]) {
+                    (Some(_), GenericValue::Null) => {
+                        remove_key(m, k);
+                        hunks.push(Hunk { path: path.clone(), value: HunkAction::Remove });
+                    }
+                    (None, GenericValue::Null) => {}
+                    (Some(target @ GenericValue::Map(_)), value @ GenericValue::Map(_)) => merge(target, value, path, hunks),
+                    (target, value) => {
+                        let value = merged(value);
+                        if target.is_none_or(|t| *t != value) {
+                            m.insert(k.clone(), value.clone());
+                            hunks.push(Hunk { path: path.clone(), value: HunkAction::Update(value) });
+                        }
+                    }
+                }
+                path.pop();
+            }
+        }
+        (doc, patch) => {
+            let value = merged(patch);
+            if *doc != value {
+                *doc = value.clone();
+                hunks.push(Hunk { path: path.clone(), value: HunkAction::Update(value) });
+            }
+        }
+    }
+}
+
+/// the patch merged to an empty document, with no null map values
+fn merged(patch: &GenericValue) -> GenericValue {
+    match patch {
+        GenericValue::Map(p) => GenericValue::Map(p.iter()
+            .filter(|(_, v)| !matches!(v, GenericValue::Null))
+            .map(|(k, v)| (k.clone(), merged(v)))
+            .collect()),
+        _ => patch.clone(),
+    }
+}
+
+/// merge patch from hunks of map paths only, the base is required to resolve text updates and swaps;
+/// error if the result can not be expressed, i.e. a map value is set to null
+pub(crate) fn to_merge_patch(hunks: &[Hunk], base: &GenericValue) -> Result<GenericValue, DocError> {
+    if let Some(h) = hunks.iter().find(|h| h.path.iter().any(|p| matches!(p, DocIndex::Idx(_)))) {
+        return Err(DocError::new(format!("Merge patch supports map paths only: {}", h)));
+    }
+    let mut target = base.clone();
+    for h in hunks {
+        h.apply(&mut target)?;
+    }
+    let patch = generate(base, &target);
+    let mut check = base.clone();
+    apply_merge_patch(&mut check, &patch)?;
+    if check != target {
+        return Err(DocError::new("Null value can not be set by merge patch"));
+    }
+    Ok(patch)
+}
+
+fn generate(base: &GenericValue, target: &GenericValue) -> GenericValue {
+    match (base, target) {
+        (GenericValue::Map(b), GenericValue::Map(t)) => {
+            let mut patch: Map = b.keys()
+                .filter(|k| !t.contains_key(*k))
+                .map(|k| (k.clone(), GenericValue::Null))
+                .collect();
+            for (k, v) in t {
+                match b.get(k) {
+                    Some(old) if old == v => {}
+                    Some(old) => { patch.insert(k.clone(), generate(old, v)); }
+                    None => { patch.insert(k.clone(), v.clone()); }
+                }
+            }
+            GenericValue::Map(patch)
+        }
+        _ => target.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::Mismatch;
+    use crate::generic::from_json;
+    use crate::merge_patch::apply_merge_patch;
+    use crate::{MismatchDoc, MismatchDocMut};
+
+    #[test]
+    fn test_apply_merge_patch() {
+        // RFC 7396 example
+        let mut doc = from_json(r#"{"title": "Goodbye!", "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"], "content": "This will be unchanged"}"#).unwrap();
+        let base = doc.clone();
+        let patch = from_json(r#"{"title": "Hello!", "phoneNumber": "+01-123-456-7890",
+            "author": {"familyName": null}, "tags": ["example"]}"#).unwrap();
+        let m = apply_merge_patch(&mut doc, &patch).unwrap();
+        assert_eq!(doc, from_json(r#"{"title": "Hello!", "author": {"givenName": "John"}, "tags": ["example"],
+            "content": "This will be unchanged", "phoneNumber": "+01-123-456-7890"}"#).unwrap());
+        assert_eq!(m.len(), 4);
+        let mut replay = base.clone();
+        m.apply_tx(&mut replay).unwrap();
+        assert_eq!(replay, doc);
+
+        let other = apply_merge_patch(&mut base.clone(), &from_json(r#"{"author": {"givenName": "Jane"}}"#).unwrap()).unwrap();
+        assert!(!m.is_intersect(&other).unwrap());
+        let other = apply_merge_patch(&mut base.clone(), &from_json(r#"{"title": "Hi!"}"#).unwrap()).unwrap();
+        assert!(m.is_intersect(&other).unwrap());
+
+        // the root replace is not a hunk
+        for (doc, patch) in [(r#"{"a": 1}"#, "[1]"), (r#"{"a": 1}"#, "null"), ("[1]", r#"{"a": 1}"#), ("\"x\"", "\"y\"")] {
+            let mut doc = from_json(doc).unwrap();
+            let before = doc.clone();
+            assert!(apply_merge_patch(&mut doc, &from_json(patch).unwrap()).is_err());
+            assert_eq!(doc, before);
+        }
+    }
+
+    #[test]
+    fn test_to_merge_patch() {
+        let base = from_json(r#"{"a": "b", "c": {"d": "e", "f": "g"}, "h": [1, 2]}"#).unwrap();
+        let input = from_json(r#"{"a": "z", "c": {"f": "g", "i": {"j": 1}}, "h": [1, 2, 3]}"#).unwrap();
+        let m = Mismatch::new(&base, &input).unwrap();
+        assert!(m.to_merge_patch(&base).is_err(), "array index path");
+
+        let base = from_json(r#"{"a": "b", "c": {"d": "e", "f": "g"}, "h": [1, 2]}"#).unwrap();
+        let input = from_json(r#"{"a": "z", "c": {"f": "g", "i": {"j": 1}}, "h": "x"}"#).unwrap();
+        let m = Mismatch::new(&base, &input).unwrap();
+        let patch = m.to_merge_patch(&base).unwrap();
+        assert_eq!(patch, from_json(r#"{"a": "z", "c": {"d": null, "i": {"j": 1}}, "h": "x"}"#).unwrap());
+        let mut doc = base.clone();
+        apply_merge_patch(&mut doc, &patch).unwrap();
+        assert_eq!(doc, input);
+
+        let m = Mismatch::new(&base, &from_json(r#"{"a": null, "c": {"d": "e", "f": "g"}, "h": [1, 2]}"#).unwrap()).unwrap();
+        assert!(m.to_merge_patch(&base).is_err(), "null value");
+    }
+}
@@ -1,12 +1,14 @@
 use std::cmp::{max, min};
-use std::collections::HashSet;
-use diffy::{create_patch, HunkRange, Patch};
+use std::fmt;
+use std::str::FromStr;
+use diffy::{create_patch, DiffOptions, HunkRange, Line, Patch};
 use crate::{DocError, MismatchDoc, MismatchDocCow};
+use crate::txt::{self, DiffOp, Lines};
 
 /// wrapper to diffy patches with intersect calculation
 ///  - Text file format as https://en.wikipedia.org/wiki/Diff
-#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
-pub struct Mismatch (String); // Patch
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Mismatch (pub(crate) String); // Patch
 
 impl MismatchDoc<String> for Mismatch {
     fn new(base: &String, input: &String) -> Result<Self, DocError>
@@ -18,11 +20,11 @@ impl MismatchDoc<String> for Mismatch {
 
 
     fn is_intersect(&self, input: &Self) -> Result<bool, DocError> {
-        Ok(is_intersect_patch(
+        is_intersect_patch(
             &Patch::from_str(self.0.as_str())
             .map_err(|e| DocError::new(e.to_string()))?,
             &Patch::from_str(input.0.as_str())
-            .map_err(|e| DocError::new(e.to_string()))?))
+            .map_err(|e| DocError::new(e.to_string()))?)
     }
 
     fn len(&self) -> usize {
@@ -32,70 +34,533 @@ impl MismatchDoc<String> for Mismatch {
 
 impl MismatchDocCow<String> for Mismatch {
     fn apply(&self, base: &String) -> Result<String, DocError> {
-        diffy::apply(base, &Patch::from_str(base.as_str())
+        diffy::apply(base, &Patch::from_str(self.0.as_str())
             .map_err(|e| DocError::new(e.to_string()))?)
             .map_err(|e| DocError::new(e.to_string()))
     }
 }
 
+/// the unified diff text
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// the received unified diff text, it has to parse with the hunk ranges from line 1
+impl FromStr for Mismatch {
+    type Err = DocError;
 
-/// Calculate real range size:
-/// The HunkRange present 3 lines before and 3 lines after a changed lines,
-/// those six lines use as marker and do not contain changes
-fn irange(h: &HunkRange, diff: &mut HashSet<usize>) -> usize {
-    let range = max(h.len(), 7);
-    for i in h.start()+range / 2 .. h.end()+1-range / 2 {
-        diff.insert(i);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let patch = Patch::from_str(s).map_err(|e| DocError::new(e.to_string()))?;
+        for h in patch.hunks() {
+            first_line(h.old_range())?;
+            first_line(h.new_range())?;
+        }
+        Ok(Mismatch(s.to_string()))
     }
-    h.len()
 }
 
-/// Text intersect calculation of two Patches:
-/// - not intersect if even and not same lines
-/// - is intersected if adding / removing lines (marked uneven) less than other
-///
-/// Returns: 
-/// - minimum number of line when ok as adding / err as removing  lines
-/// - set of changing lines
-fn ranges(patch: &Patch<str>) -> (Result<usize, usize>, HashSet<usize>) {
-    let mut diff = HashSet::new();
-    let mut u = false;
-    let mut min_u = < usize > :: MAX - 1usize;
-    let mut max_u = 0;
-    
-    for h in patch.hunks() {
-        let old = irange(&h.old_range(), &mut diff);
-        let new = irange(&h.new_range(), &mut diff);
-        if old !=new {
-            u = true;
-            min_u = min(min_u, old);
-            min_u = min(min_u, new);
-        } else {
-            max_u = max(max_u, old);
-            max_u = max(max_u, new);
+
+/// the file of the multi-file patch
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FilePatch {
+    /// path before the change, none for the new file
+    pub old_path: Option<String>,
+    /// path after the change, none for the deleted file
+    pub new_path: Option<String>,
+    /// file mode before the change if it is changed, or of the deleted file
+    pub old_mode: Option<String>,
+    /// file mode after the change if it is changed, or of the new file
+    pub new_mode: Option<String>,
+    /// the content is binary, the binary patch is not kept
+    pub binary: bool,
+    /// the `---`, `+++` and hunks of the content change, empty if the content is the same
+    pub patch: Mismatch,
+}
+
+impl FilePatch {
+    fn new(old_path: Option<String>, new_path: Option<String>) -> Self {
+        FilePatch { old_path, new_path, old_mode: None, new_mode: None, binary: false, patch: Mismatch(String::new()) }
+    }
+
+    /// the path in the base tree, the new path for the new file
+    pub fn path(&self) -> &str {
+        self.old_path.as_deref().or(self.new_path.as_deref()).unwrap_or_default()
+    }
+
+    /// the change of the same base file which does not commute with the other
+    fn is_intersect(&self, other: &Self) -> Result<bool, DocError> {
+        if self.old_path.is_none() || self.new_path.is_none() || self.new_path != other.new_path
+            || other.old_path.is_none() || other.new_path.is_none()
+            || (self.new_mode.is_some() && other.new_mode.is_some() && self.new_mode != other.new_mode)
+            || self.binary || other.binary {
+            return Ok(true);
+        }
+        if self.patch.0.is_empty() || other.patch.0.is_empty() {
+            return Ok(false);
         }
+        self.patch.is_intersect(&other.patch)
+    }
+}
+
+/// multi-file unified diff as `git diff` or `diff -ruN` outputs
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct MultiMismatch(pub(crate) Vec<FilePatch>);
+
+impl MultiMismatch {
+    /// split to the files by `diff --git` headers, or by `---` and `+++` headers of the plain diff;
+    /// the text before the first file as the commit message is skipped
+    pub fn parse(text: &str) -> Result<Self, DocError> {
+        let mut files: Vec<FilePatch> = Vec::new();
+        let mut git = false; // the current file has the `diff --git` header
+        let (mut old_left, mut new_left) = (0usize, 0usize);
+        let mut skip = false; // the binary patch data
+        for line in text.split_inclusive('\n') {
+            let l = line.trim_end_matches(['\n', '\r']);
+            if old_left > 0 || new_left > 0 {
+                let file = files.last_mut().ok_or_else(|| DocError::new("hunk out of file"))?;
+                match l.chars().next() {
+                    Some('-') => old_left = old_left.checked_sub(1).ok_or_else(|| DocError::new(format!("hunk too long: {}", l)))?,
+                    Some('+') => new_left = new_left.checked_sub(1).ok_or_else(|| DocError::new(format!("hunk too long: {}", l)))?,
+                    Some('\\') => {}
+                    _ if old_left > 0 && new_left > 0 => (old_left, new_left) = (old_left - 1, new_left - 1),
+                    _ => return Err(DocError::new(format!("hunk too short at: {}", l))),
+                }
+                file.patch.0.push_str(line);
+                continue;
+            }
+            if let Some(paths) = l.strip_prefix("diff --git ") {
+                let (old, new) = git_paths(paths).ok_or_else(|| DocError::new(format!("wrong header: {}", l)))?;
+                files.push(FilePatch::new(Some(old), Some(new)));
+                (git, skip) = (true, false);
+                continue;
+            }
+            if skip {
+                continue;
+            }
+            let started = files.last().is_some_and(|f| git && f.patch.0.is_empty());
+            if let Some(path) = l.strip_prefix("--- ") {
+                if !started {
+                    files.push(FilePatch::new(None, None));
+                    git = false;
+                }
+                let file = files.last_mut().unwrap_or_else(|| unreachable!());
+                if !git {
+                    file.old_path = header_path(path);
+                } else if header_path(path).is_none() {
+                    file.old_path = None;
+                }
+                file.patch.0.push_str(line);
+            } else if let Some(path) = l.strip_prefix("+++ ") {
+                let file = files.last_mut().filter(|f| f.patch.0.starts_with("--- "))
+                    .ok_or_else(|| DocError::new(format!("no --- before: {}", l)))?;
+                if !git {
+                    file.new_path = header_path(path);
+                    // a/ and b/ prefixes of git without the `diff --git` header
+                    if let (Some(o), Some(n)) = (file.old_path.as_deref().and_then(|p| p.strip_prefix("a/")),
+                        file.new_path.as_deref().and_then(|p| p.strip_prefix("b/"))) {
+                        (file.old_path, file.new_path) = (Some(o.to_string()), Some(n.to_string()));
+                    } else if file.old_path.is_none() {
+                        file.new_path = file.new_path.take().map(|p| p.strip_prefix("b/").map(str::to_string).unwrap_or(p));
+                    } else if file.new_path.is_none() {
+                        file.old_path = file.old_path.take().map(|p| p.strip_prefix("a/").map(str::to_string).unwrap_or(p));
+                    }
+                } else if header_path(path).is_none() {
+                    file.new_path = None;
+                }
+                file.patch.0.push_str(line);
+            } else if let Some(range) = l.strip_prefix("@@ -") {
+                let file = files.last_mut().filter(|f| f.patch.0.contains("\n+++ "))
+                    .ok_or_else(|| DocError::new(format!("no +++ before: {}", l)))?;
+                (old_left, new_left) = hunk_lens(range).ok_or_else(|| DocError::new(format!("wrong hunk header: {}", l)))?;
+                file.patch.0.push_str(line);
+            } else if l.starts_with('\\') && files.last().is_some_and(|f| f.patch.0.contains("\n@@ ")) {
+                files.last_mut().unwrap_or_else(|| unreachable!()).patch.0.push_str(line);
+            } else if let Some(file) = files.last_mut().filter(|_| started) {
+                if let Some(mode) = l.strip_prefix("new file mode ") {
+                    (file.old_path, file.new_mode) = (None, Some(mode.to_string()));
+                } else if let Some(mode) = l.strip_prefix("deleted file mode ") {
+                    (file.new_path, file.old_mode) = (None, Some(mode.to_string()));
+                } else if let Some(mode) = l.strip_prefix("old mode ") {
+                    file.old_mode = Some(mode.to_string());
+                } else if let Some(mode) = l.strip_prefix("new mode ") {
+                    file.new_mode = Some(mode.to_string());
+                } else if let Some(path) = l.strip_prefix("rename from ").or(l.strip_prefix("copy from ")) {
+                    file.old_path = Some(path.to_string());
+                } else if let Some(path) = l.strip_prefix("rename to ").or(l.strip_prefix("copy to ")) {
+                    file.new_path = Some(path.to_string());
+                } else if l.starts_with("Binary files ") || l == "GIT binary patch" {
+                    (file.binary, skip) = (true, true);
+                }
+                // index, similarity and dissimilarity lines do not change the file
+            }
+        }
+        if old_left > 0 || new_left > 0 {
+            return Err(DocError::new("the last hunk is incomplete"));
+        }
+        Ok(MultiMismatch(files))
+    }
+
+    pub fn files(&self) -> &[FilePatch] {
+        &self.0
+    }
+
+    /// the base paths of the files changed by both patches which do not commute,
+    /// or created by both with the same path
+    pub fn intersections(&self, other: &Self) -> Result<Vec<String>, DocError> {
+        let mut out = Vec::new();
+        for a in &self.0 {
+            for b in &other.0 {
+                let same = a.path() == b.path() && a.old_path.is_some() == b.old_path.is_some();
+                let created = a.new_path.is_some() && a.new_path == b.new_path;
+                if ((same && a.is_intersect(b)?) || (!same && created)) && !out.iter().any(|p| p == a.path()) {
+                    out.push(a.path().to_string());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// return false if no file changes of the both patches intersect, so they apply in any order
+    pub fn is_intersect(&self, other: &Self) -> Result<bool, DocError> {
+        Ok(!self.intersections(other)?.is_empty())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// old and new paths of `a/old b/new`, the path with spaces splits in the middle if the same
+fn git_paths(paths: &str) -> Option<(String, String)> {
+    let paths = paths.trim_end();
+    let half = paths.len() / 2;
+    if paths.is_char_boundary(half) && paths.as_bytes().get(half) == Some(&b' ')
+        && paths[..half].strip_prefix("a/") == paths[half + 1..].strip_prefix("b/") {
+        let old = paths[2..half].to_string();
+        return Some((old.clone(), old));
+    }
+    let (old, new) = paths.rsplit_once(" b/")?;
+    Some((old.strip_prefix("a/")?.to_string(), new.to_string()))
+}
+
+/// path of `---` and `+++` header without the timestamp, none for /dev/null
+fn header_path(path: &str) -> Option<String> {
+    let path = path.split('\t').next().unwrap_or_default().trim_end();
+    (path != "/dev/null").then(|| path.to_string())
+}
+
+/// old and new lines count of `-1,3 +1,4 @@` hunk header
+fn hunk_lens(range: &str) -> Option<(usize, usize)> {
+    let (old, rest) = range.split_once(" +")?;
+    let (new, _) = rest.split_once(" @@")?;
+    let len = |r: &str| r.split_once(',').map_or(Some(1), |(_, l)| l.parse().ok());
+    Some((len(old)?, len(new)?))
+}
+
+/// how far the hunks of the patch may move from their lines, as GNU patch does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApplyOptions {
+    /// the lines the hunk may move from the line of its header, shifted by the offset of the previous hunk
+    pub max_offset: usize,
+    /// the context lines at the hunk ends may not match, same as `patch --fuzz`
+    pub fuzz: usize,
+}
+
+impl Default for ApplyOptions {
+    fn default() -> Self {
+        ApplyOptions { max_offset: usize::MAX, fuzz: 2 }
+    }
+}
+
+impl ApplyOptions {
+    pub fn new(max_offset: usize, fuzz: usize) -> Self {
+        ApplyOptions { max_offset, fuzz }
     }
+}
 
-    (if u { Err(min_u) } else {Ok(max_u)}, diff)
+/// how the hunk of the patch applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkStatus {
+    /// at the lines of the hunk header
+    Applied,
+    /// moved by the lines from the hunk header
+    Offset(isize),
+    /// the `fuzz` context lines at the hunk ends are ignored, may be moved as well
+    Fuzz { offset: isize, fuzz: usize },
+    /// the context between the changes of the hunk is changed, they applied one by one
+    Split,
+    /// the changes are in the base already, i.e. the same changes of the other patch applied
+    Present,
+    /// the context or the removed lines are not found, the hunk is skipped
+    Rejected,
 }
 
+impl Mismatch {
+    /// apply the hunks found in the base even if moved or with the changed context,
+    /// the hunk with the changed context between its changes applies them one by one,
+    /// the rejected hunks are skipped; returns the text with the status of every hunk
+    pub fn apply_with(&self, base: &str, options: &ApplyOptions) -> Result<(String, Vec<HunkStatus>), DocError> {
+        let patch = Patch::from_str(self.0.as_str()).map_err(|e| DocError::new(e.to_string()))?;
+        let image: Vec<&str> = base.split_inclusive('\n').collect();
+        let mut out = String::with_capacity(base.len());
+        let mut done = 0; // the base lines before are in the output
+        let mut shift = 0; // the offset of the last applied hunk
+        let mut status = Vec::with_capacity(patch.hunks().len());
+        for h in patch.hunks() {
+            let start = first_line(h.old_range())?;
+            let whole = [(start, h.lines())];
+            let pieces = split_hunk(start, h.lines());
+            let applied = apply_lines(&image, &whole, done, shift, options, false).map(|(text, at, offset, fuzz, _)| {
+                let s = match (offset, fuzz) {
+                    (0, 0) => HunkStatus::Applied,
+                    (_, 0) => HunkStatus::Offset(offset),
+                    _ => HunkStatus::Fuzz { offset, fuzz },
+                };
+                (text, at, offset, s)
+            }).or_else(|| apply_lines(&image, if pieces.len() > 1 { &pieces } else { &whole }, done, shift, options, true)
+                .map(|(text, at, offset, _, present)| {
+                    (text, at, offset, if present == pieces.len().max(1) { HunkStatus::Present } else { HunkStatus::Split })
+                }));
+            match applied {
+                Some((text, at, offset, s)) => {
+                    out.push_str(&text);
+                    (done, shift) = (at, offset);
+                    status.push(s);
+                }
+                None => status.push(HunkStatus::Rejected),
+            }
+        }
+        image[done..].iter().for_each(|l| out.push_str(l));
+        Ok((out, status))
+    }
+
+    /// the unified diff of the text patch applied to the base, with `context` lines around the changes
+    pub fn from_txt(patch: &txt::Mismatch, base: &str, context: usize) -> Result<Self, DocError> {
+        let input = patch.apply(&base.to_string())?;
+        Ok(Mismatch(DiffOptions::new().set_context_len(context).create_patch(base, &input).to_string()))
+    }
 
-/// check for intersections i.e. unable to implement commutative for two patches
-/// use diffy::apply(base_image, &patch) to modify
-/// todo ignore same changes on same line include same line deletion
-fn is_intersect_patch(patch_a: &Patch<str>, patch_b: &Patch<str>) -> bool {
-    let (even_a, diff_a) = ranges(patch_a);
-    let (even_b, diff_b) = ranges(patch_b);
-    let intersect = match even_a {
-        Ok(a) => match even_b {
-            Ok(b) => a == b, // neither patches adding/removing, needs check modified lines
-            Err(b) => a >= b, // only B patch adding/removing  
-        },
-        Err(a) => match even_b {
-            Ok(b) => a <= b, // only A patch adding/removing  
-            Err(_) => true, // both patches adding/removing, the intersect is essential
+    /// the text patch of the same change against the base: the changed lines of the hunks as `Update`, `Remove` and `Insert`
+    /// with the end-of-line changes, or the diff of the patched text if the lines split otherwise;
+    /// the hunks apply at the lines of their headers only, so the hunks without context are exact
+    pub fn to_txt(&self, base: &str) -> Result<txt::Mismatch, DocError> {
+        let patch = Patch::from_str(self.0.as_str()).map_err(|e| DocError::new(e.to_string()))?;
+        let (input, status) = self.apply_with(base, &ApplyOptions::new(0, 0))?;
+        if let Some(i) = status.iter().position(|s| *s != HunkStatus::Applied) {
+            return Err(DocError::new(format!("hunk {} does not apply at its lines", i + 1)));
+        }
+        let mut ops = Vec::new();
+        for h in patch.hunks() {
+            let mut index = first_line(h.new_range())?;
+            let lines = h.lines();
+            let mut i = 0;
+            while i < lines.len() {
+                let start = i + lines[i..].iter().take_while(|l| matches!(l, Line::Context(_))).count();
+                let end = start + lines[start..].iter().take_while(|l| !matches!(l, Line::Context(_))).count();
+                let (old, new) = sides(&lines[start..end]);
+                index = txt_ops(index + start - i, &old, &new, &mut ops);
+                i = end;
+            }
+        }
+        if txt::push_eols(&Lines::parse(base), &Lines::parse(&input), &mut ops) {
+            Ok(txt::Mismatch(ops))
+        } else {
+            txt::Mismatch::new(&base.to_string(), &input)
         }
+    }
+}
+
+/// the changed lines at the index as the line ops of the text patch, `Update` for the paired lines;
+/// the last line without the end-of-line takes the place of the empty last line of the text ending with it
+fn txt_ops(index: usize, old: &[&str], new: &[&str], ops: &mut Vec<DiffOp>) -> usize {
+    let no_eol = |lines: &[&str]| lines.last().is_some_and(|l| !l.ends_with('\n'));
+    let content = |lines: &[&str]| -> Vec<String> {
+        lines.iter().map(|l| l.strip_suffix('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)).unwrap_or(l).to_string()).collect()
     };
-    // if not intersect yet, check modified lines joints
-    intersect || !diff_a.is_disjoint(&diff_b)
+    let (mut old_lines, mut new_lines) = (content(old), content(new));
+    match (no_eol(old), no_eol(new)) {
+        (true, false) => new_lines.push(String::new()),
+        (false, true) => old_lines.push(String::new()),
+        _ => {}
+    }
+    let paired = min(old_lines.len(), new_lines.len());
+    let removed = old_lines.len() - paired;
+    let len = new_lines.len();
+    for (i, (o, n)) in old_lines.into_iter().zip(new_lines.iter_mut()).enumerate() {
+        if o != *n {
+            ops.push(DiffOp::Update { index: index + i, value: std::mem::take(n) });
+        }
+    }
+    ops.extend((0..removed).map(|_| DiffOp::Remove { index: index + paired }));
+    ops.extend(new_lines.into_iter().enumerate().skip(paired).map(|(i, value)| DiffOp::Insert { index: index + i, value }));
+    index + len
+}
+
+/// the changes of the hunk with up to 3 context lines around, the context between them is shared
+fn split_hunk<'a, 'b>(start: usize, lines: &'b [Line<'a, str>]) -> Vec<(usize, &'b [Line<'a, str>])> {
+    let mut runs = Vec::new(); // the ranges of the changed lines
+    let mut i = 0;
+    while i < lines.len() {
+        i += lines[i..].iter().take_while(|l| matches!(l, Line::Context(_))).count();
+        let end = i + lines[i..].iter().take_while(|l| !matches!(l, Line::Context(_))).count();
+        if end > i {
+            runs.push((i, end));
+        }
+        i = end;
+    }
+    (0..runs.len()).map(|k| {
+        let (s, e) = runs[k];
+        let from = s - min(s - if k == 0 { 0 } else { runs[k - 1].1 }, 3);
+        let to = e + min(if k + 1 == runs.len() { lines.len() } else { runs[k + 1].0 } - e, 3);
+        (start + lines[..from].iter().filter(|l| !matches!(l, Line::Insert(_))).count(), &lines[from..to])
+    }).collect()
+}
+
+/// the new text from the base line `from` with the lines of the hunks at the base lines;
+/// the base line after the last change, as the context after it may be the context of the next hunk,
+/// the offset and the fuzz of the last hunk, and how many hunks are in the base already if allowed
+fn apply_lines(image: &[&str], hunks: &[(usize, &[Line<str>])], mut from: usize, mut shift: isize, options: &ApplyOptions,
+    allow_present: bool) -> Option<(String, usize, isize, usize, usize)> {
+    let mut out = String::new();
+    let (mut fuzz, mut present) = (0, 0);
+    for (start, lines) in hunks {
+        let (old, new) = sides(lines);
+        let (_, tail) = context(&old, &new);
+        let (pos, offset, f, lead, old, new) = match find_hunk(image, &old, &new, *start, from, shift, options) {
+            Some((pos, offset, f, lead, _)) => (pos, offset, f, lead, old, new),
+            // the same change is applied, i.e. by the other patch
+            None if allow_present => {
+                let (pos, offset, f, lead, _) = find_hunk(image, &new, &old, *start, from, shift, options)?;
+                present += 1;
+                (pos, offset, f, lead, new.clone(), new)
+            }
+            None => return None,
+        };
+        image[from..pos].iter().for_each(|l| out.push_str(l));
+        new[lead..new.len() - tail].iter().for_each(|l| out.push_str(l));
+        (from, shift, fuzz) = (pos + old.len() - lead - tail, offset, max(fuzz, f));
+    }
+    Some((out, from, shift, fuzz, present))
+}
+
+/// the base and the changed lines of the hunk
+fn sides<'a>(lines: &[Line<'a, str>]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let old = lines.iter().filter_map(|l| match l {
+        Line::Context(s) | Line::Delete(s) => Some(*s),
+        Line::Insert(_) => None,
+    }).collect();
+    let new = lines.iter().filter_map(|l| match l {
+        Line::Context(s) | Line::Insert(s) => Some(*s),
+        Line::Delete(_) => None,
+    }).collect();
+    (old, new)
+}
+
+/// the same lines at the start and at the end of the both sides of the hunk, the context lines
+fn context(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let lead = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let rest = min(old.len(), new.len()) - lead;
+    (lead, min(old.iter().rev().zip(new.iter().rev()).take_while(|(a, b)| a == b).count(), rest))
+}
+
+/// the base line to apply the hunk at from the line `from`, the offset from the hunk start,
+/// the fuzz and the context lines ignored at the start and at the end of the hunk
+fn find_hunk(image: &[&str], old: &[&str], new: &[&str], start: usize, from: usize, shift: isize, options: &ApplyOptions)
+    -> Option<(usize, isize, usize, usize, usize)> {
+    let (lead_context, trail_context) = context(old, new);
+    // a context line is kept at the both ends to match the change place
+    let (lead_context, trail_context) = (lead_context.saturating_sub(1), trail_context.saturating_sub(1));
+    for fuzz in 0..=min(options.fuzz, max(lead_context, trail_context)) {
+        let (lead, trail) = (min(fuzz, lead_context), min(fuzz, trail_context));
+        let pre = &old[lead..old.len() - trail];
+        let nominal = (start + lead) as isize;
+        let Some(last) = image.len().checked_sub(pre.len()) else {
+            continue; // longer than the base
+        };
+        let last = last as isize;
+        let center = nominal + shift;
+        let mut d = 0usize;
+        while d <= options.max_offset {
+            let (up, down) = (center + d as isize, center - d as isize);
+            if up > last && down < from as isize {
+                break;
+            }
+            for at in if d == 0 { vec![up] } else { vec![up, down] } {
+                if at >= from as isize && at <= last && image[at as usize..at as usize + pre.len()] == *pre {
+                    return Some((at as usize, at - nominal, fuzz, lead, trail));
+                }
+            }
+            d += 1;
+        }
+    }
+    None
+}
+
+/// the change of the base lines `start..end` to the inserted lines, zero based
+#[derive(Debug, PartialEq)]
+struct Edit<'a> {
+    start: usize,
+    end: usize,
+    insert: Vec<&'a str>,
+}
+
+/// zero based line of the range, the empty range is after its line;
+/// the lines are counted from 1, so only the empty range starts at 0
+fn first_line(range: HunkRange) -> Result<usize, DocError> {
+    if range.is_empty() {
+        return Ok(range.start());
+    }
+    range.start().checked_sub(1).ok_or_else(|| DocError::new(format!("hunk range {} starts at line 0", range)))
+}
+
+/// the removed and inserted lines of the hunks in the base lines, the context lines are not changes
+fn edits<'a>(patch: &'a Patch<'_, str>) -> Result<Vec<Edit<'a>>, DocError> {
+    let mut out = Vec::new();
+    for h in patch.hunks() {
+        let mut line = first_line(h.old_range())?;
+        let mut edit: Option<Edit> = None;
+        for l in h.lines() {
+            match l {
+                Line::Context(_) => {
+                    out.extend(edit.take());
+                    line += 1;
+                }
+                Line::Delete(_) => {
+                    edit.get_or_insert(Edit { start: line, end: line, insert: Vec::new() }).end += 1;
+                    line += 1;
+                }
+                Line::Insert(s) => edit.get_or_insert(Edit { start: line, end: line, insert: Vec::new() }).insert.push(s),
+            }
+        }
+        out.extend(edit);
+    }
+    Ok(out)
+}
+
+/// the base lines `start..end` the hunk matches to apply, the context and the removed lines
+fn hunk_lines(patch: &Patch<'_, str>) -> Result<Vec<(usize, usize)>, DocError> {
+    patch.hunks().iter().map(|h| first_line(h.old_range()).map(|start| (start, start + h.old_range().len()))).collect()
+}
+
+/// check for intersections i.e. unable to implement commutative for two patches:
+/// the changes of the same or adjacent base lines intersect, same as git merge conflicts,
+/// and the changes of the lines the hunk of the other patch matches, the context included,
+/// as `apply` needs all of them unchanged; the same change in both patches does not, apply it
+/// after the other one with `Mismatch::apply_with` which finds it applied already.
+/// The context of the hunk repeated in the base nearer than the lines added or removed before it
+/// by the other patch may take the hunk, as for GNU patch
+fn is_intersect_patch(patch_a: &Patch<str>, patch_b: &Patch<str>) -> Result<bool, DocError> {
+    let (edits_a, edits_b) = (edits(patch_a)?, edits(patch_b)?);
+    // the inserted lines break the hunk inside it only
+    let touch = |edits: &[Edit], other: &[Edit], hunks: &[(usize, usize)]| edits.iter()
+        .filter(|e| !other.contains(e))
+        .any(|e| hunks.iter().any(|&(start, end)| e.start < end && start < e.end));
+    Ok(edits_a.iter().any(|a| edits_b.iter().any(|b| a != b && a.start <= b.end && b.start <= a.end))
+        || touch(&edits_a, &edits_b, &hunk_lines(patch_b)?) || touch(&edits_b, &edits_a, &hunk_lines(patch_a)?))
 }
@@ -0,0 +1,498 @@
+//! protobuf binary encoding by `proto/diff_doc.proto`, the same bytes `c/diff_apply.c` consumes
+
+use prost::Message;
+use crate::{diff, generic, txt, DocError};
+
+mod diff_doc;
+
+pub use diff_doc::*;
+
+impl crate::Mismatches {
+    /// protobuf binary encoding
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Mismatches::from(self).encode_to_vec()
+    }
+
+    /// decode from protobuf binary encoding
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DocError> {
+        Mismatches::decode(bytes).map_err(|e| DocError::new(e.to_string()))?.try_into()
+    }
+}
+
+fn missing(name: &str) -> DocError {
+    DocError::new(format!("{} kind is not set", name))
+}
+
+fn to_usize(v: u64) -> Result<usize, DocError> {
+    usize::try_from(v).map_err(|_| DocError::new(format!("index {} out of range", v)))
+}
+
+impl From<&crate::Mismatches> for Mismatches {
+    fn from(value: &crate::Mismatches) -> Self {
+        let kind = match value {
+            #[cfg(feature="patch")]
+            crate::Mismatches::Patch(p) => PatchMismatch::parse(&p.0)
+                .map_or_else(|| mismatches::Kind::Patch(p.0.clone()), mismatches::Kind::Unified),
+            crate::Mismatches::Doc(d) => mismatches::Kind::Doc(DocMismatch { hunks: d.0.iter().map(Hunk::from).collect() }),
+            crate::Mismatches::Text(t) => mismatches::Kind::Text(TxtMismatch::from(t.0.as_slice())),
+        };
+        Mismatches { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<Mismatches> for crate::Mismatches {
+    type Error = DocError;
+
+    fn try_from(value: Mismatches) -> Result<Self, Self::Error> {
+        match value.kind.ok_or_else(|| missing("Mismatches"))? {
+            #[cfg(feature="patch")]
+            mismatches::Kind::Patch(p) => Ok(crate::Mismatches::Patch(crate::patch::Mismatch(p))),
+            #[cfg(feature="patch")]
+            mismatches::Kind::Unified(u) => Ok(crate::Mismatches::Patch(crate::patch::Mismatch(u.render()?))),
+            #[cfg(not(feature="patch"))]
+            mismatches::Kind::Patch(_) | mismatches::Kind::Unified(_) => Err(DocError::new("Patch requires the patch feature")),
+            mismatches::Kind::Doc(d) => Ok(crate::Mismatches::Doc(diff::Mismatch(
+                d.hunks.into_iter().map(generic::Hunk::try_from).collect::<Result<_, _>>()?))),
+            mismatches::Kind::Text(t) => Ok(crate::Mismatches::Text(txt::Mismatch(t.try_into()?))),
+        }
+    }
+}
+
+#[cfg(feature="patch")]
+impl PatchMismatch {
+    /// the hunks of the unified diff if it renders back to the same text, otherwise it stays the raw text
+    fn parse(text: &str) -> Option<Self> {
+        let patch = diffy::Patch::from_str(text).ok()?;
+        let line = |l: &diffy::Line<str>| PatchLine { kind: Some(match l {
+            diffy::Line::Context(s) => patch_line::Kind::Context(s.to_string()),
+            diffy::Line::Delete(s) => patch_line::Kind::Delete(s.to_string()),
+            diffy::Line::Insert(s) => patch_line::Kind::Insert(s.to_string()),
+        })};
+        let out = PatchMismatch {
+            original: patch.original().map(|s| s.to_string()),
+            modified: patch.modified().map(|s| s.to_string()),
+            hunks: patch.hunks().iter().map(|h| PatchHunk {
+                old_start: h.old_range().start() as u64,
+                old_len: h.old_range().len() as u64,
+                new_start: h.new_range().start() as u64,
+                new_len: h.new_range().len() as u64,
+                function_context: h.function_context().map(|s| s.to_string()),
+                lines: h.lines().iter().map(line).collect(),
+            }).collect(),
+        };
+        out.render().ok().filter(|t| t == text).map(|_| out)
+    }
+
+    /// unified diff text the same as diffy renders
+    fn render(&self) -> Result<String, DocError> {
+        let range = |start: u64, len: u64| if len == 1 { start.to_string() } else { format!("{},{}", start, len) };
+        let mut out = String::new();
+        if let Some(o) = &self.original {
+            out.push_str(&format!("--- {}\n", o));
+        }
+        if let Some(m) = &self.modified {
+            out.push_str(&format!("+++ {}\n", m));
+        }
+        for h in &self.hunks {
+            out.push_str(&format!("@@ -{} +{} @@", range(h.old_start, h.old_len), range(h.new_start, h.new_len)));
+            if let Some(ctx) = &h.function_context {
+                out.push_str(&format!("  {}", ctx));
+            }
+            out.push('\n');
+            for l in &h.lines {
+                let (sign, line) = match l.kind.as_ref().ok_or_else(|| missing("PatchLine"))? {
+                    patch_line::Kind::Context(s) => (' ', s),
+                    patch_line::Kind::Delete(s) => ('-', s),
+                    patch_line::Kind::Insert(s) => ('+', s),
+                };
+                out.push(sign);
+                out.push_str(line);
+                if !line.ends_with('\n') {
+                    out.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl From<&generic::Hunk> for Hunk {
+    fn from(value: &generic::Hunk) -> Self {
+        Hunk { path: value.path.iter().map(DocIndex::from).collect(), value: Some(HunkAction::from(&value.value)) }
+    }
+}
+
+impl TryFrom<Hunk> for generic::Hunk {
+    type Error = DocError;
+
+    fn try_from(value: Hunk) -> Result<Self, Self::Error> {
+        Ok(generic::Hunk {
+            path: value.path.into_iter().map(generic::DocIndex::try_from).collect::<Result<_, _>>()?,
+            value: value.value.ok_or_else(|| missing("HunkAction"))?.try_into()?,
+        })
+    }
+}
+
+impl From<&generic::DocIndex> for DocIndex {
+    fn from(value: &generic::DocIndex) -> Self {
+        let kind = match value {
+            generic::DocIndex::Name(n) => doc_index::Kind::Name(n.clone()),
+            generic::DocIndex::Idx(i) => doc_index::Kind::Idx(*i as u64),
+        };
+        DocIndex { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<DocIndex> for generic::DocIndex {
+    type Error = DocError;
+
+    fn try_from(value: DocIndex) -> Result<Self, Self::Error> {
+        match value.kind.ok_or_else(|| missing("DocIndex"))? {
+            doc_index::Kind::Name(n) => Ok(generic::DocIndex::Name(n)),
+            doc_index::Kind::Idx(i) => Ok(generic::DocIndex::Idx(to_usize(i)?)),
+        }
+    }
+}
+
+impl From<&generic::HunkAction> for HunkAction {
+    fn from(value: &generic::HunkAction) -> Self {
+        let kind = match value {
+            generic::HunkAction::Remove => hunk_action::Kind::Remove(true),
+            generic::HunkAction::Update(v) => hunk_action::Kind::Update(v.into()),
+            generic::HunkAction::UpdateTxt(ops) => hunk_action::Kind::UpdateTxt(ops.as_slice().into()),
+            generic::HunkAction::Insert(v) => hunk_action::Kind::Insert(v.into()),
+            generic::HunkAction::Swap(i) => hunk_action::Kind::Swap(i.into()),
+            generic::HunkAction::Clone(i) => hunk_action::Kind::Clone(i.into()),
+            generic::HunkAction::Move(i) => hunk_action::Kind::Move(i.into()),
+        };
+        HunkAction { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<HunkAction> for generic::HunkAction {
+    type Error = DocError;
+
+    fn try_from(value: HunkAction) -> Result<Self, Self::Error> {
+        Ok(match value.kind.ok_or_else(|| missing("HunkAction"))? {
+            hunk_action::Kind::Remove(_) => generic::HunkAction::Remove,
+            hunk_action::Kind::Update(v) => generic::HunkAction::Update(v.try_into()?),
+            hunk_action::Kind::UpdateTxt(t) => generic::HunkAction::UpdateTxt(t.try_into()?),
+            hunk_action::Kind::Insert(v) => generic::HunkAction::Insert(v.try_into()?),
+            hunk_action::Kind::Swap(i) => generic::HunkAction::Swap(i.try_into()?),
+            hunk_action::Kind::Clone(i) => generic::HunkAction::Clone(i.try_into()?),
+            hunk_action::Kind::Move(i) => generic::HunkAction::Move(i.try_into()?),
+        })
+    }
+}
+
+impl From<&generic::GenericValue> for GenericValue {
+    fn from(value: &generic::GenericValue) -> Self {
+        let kind = match value {
+            generic::GenericValue::Numeric(n) => generic_value::Kind::Numeric(n.0.clone()),
+            generic::GenericValue::Map(m) => generic_value::Kind::Map(GenericMap {
+                fields: m.iter().map(|(k, v)| GenericMapEntry { key: k.clone(), value: Some(v.into()) }).collect(),
+            }),
+            generic::GenericValue::Array(a) => generic_value::Kind::Array(GenericArray { items: a.iter().map(GenericValue::from).collect() }),
+            generic::GenericValue::Boolean(b) => generic_value::Kind::Boolean(*b),
+            generic::GenericValue::StringValue(s) => generic_value::Kind::String(s.clone()),
+            generic::GenericValue::Null => generic_value::Kind::Null(true),
+        };
+        GenericValue { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<GenericValue> for generic::GenericValue {
+    type Error = DocError;
+
+    fn try_from(value: GenericValue) -> Result<Self, Self::Error> {
+        Ok(match value.kind.ok_or_else(|| missing("GenericValue"))? {
+            generic_value::Kind::Numeric(n) => generic::GenericValue::Numeric(generic::NumericString(n)),
+            generic_value::Kind::Map(m) => generic::GenericValue::Map(m.fields.into_iter()
+                .map(|e| Ok((e.key, e.value.ok_or_else(|| missing("GenericValue"))?.try_into()?)))
+                .collect::<Result<_, DocError>>()?),
+            generic_value::Kind::Array(a) => generic::GenericValue::Array(
+                a.items.into_iter().map(generic::GenericValue::try_from).collect::<Result<_, _>>()?),
+            generic_value::Kind::Boolean(b) => generic::GenericValue::Boolean(b),
+            generic_value::Kind::String(s) => generic::GenericValue::StringValue(s),
+            generic_value::Kind::Null(_) => generic::GenericValue::Null,
+        })
+    }
+}
+
+impl From<&[txt::DiffOp]> for TxtMismatch {
+    fn from(value: &[txt::DiffOp]) -> Self {
+        TxtMismatch { ops: value.iter().map(DiffOp::from).collect() }
+    }
+}
+
+impl TryFrom<TxtMismatch> for Vec<txt::DiffOp> {
+    type Error = DocError;
+
+    fn try_from(value: TxtMismatch) -> Result<Self, Self::Error> {
+        value.ops.into_iter().map(txt::DiffOp::try_from).collect()
+    }
+}
+
+impl From<&txt::DiffOp> for DiffOp {
+    fn from(value: &txt::DiffOp) -> Self {
+        let kind = match value.clone() {
+            txt::DiffOp::Remove { index } => diff_op::Kind::Remove(DiffOpRemove { index: index as u64 }),
+            txt::DiffOp::Insert { index, value } => diff_op::Kind::Insert(DiffOpInsert { index: index as u64, value }),
+            txt::DiffOp::Update { index, value } => diff_op::Kind::Update(DiffOpUpdate { index: index as u64, value }),
+            txt::DiffOp::Append { index, pos, value } =>
+                diff_op::Kind::Append(DiffOpAppend { index: index as u64, pos: pos as u64, value }),
+            txt::DiffOp::Delete { index, pos, len } =>
+                diff_op::Kind::Delete(DiffOpDelete { index: index as u64, pos: pos as u64, len: len as u64 }),
+            txt::DiffOp::Replace { index, pos, len, value } =>
+                diff_op::Kind::Replace(DiffOpReplace { index: index as u64, pos: pos as u64, len: len as u64, value }),
+            txt::DiffOp::Eol { index, value } => diff_op::Kind::Eol(DiffOpEol { index: index as u64, value }),
+        };
+        DiffOp { kind: Some(kind) }
+    }
+}
+
+impl TryFrom<DiffOp> for txt::DiffOp {
+    type Error = DocError;
+
+    fn try_from(value: DiffOp) -> Result<Self, Self::Error> {
+        Ok(match value.kind.ok_or_else(|| missing("DiffOp"))? {
+            diff_op::Kind::Remove(o) => txt::DiffOp::Remove { index: to_usize(o.index)? },
+            diff_op::Kind::Insert(o) => txt::DiffOp::Insert { index: to_usize(o.index)?, value: o.value },
+            diff_op::Kind::Update(o) => txt::DiffOp::Update { index: to_usize(o.index)?, value: o.value },
+            diff_op::Kind::Append(o) => txt::DiffOp::Append { index: to_usize(o.index)?, pos: to_usize(o.pos)?, value: o.value },
+            diff_op::Kind::Delete(o) => txt::DiffOp::Delete { index: to_usize(o.index)?, pos: to_usize(o.pos)?, len: to_usize(o.len)? },
+            diff_op::Kind::Replace(o) =>
+                txt::DiffOp::Replace { index: to_usize(o.index)?, pos: to_usize(o.pos)?, len: to_usize(o.len)?, value: o.value },
+            diff_op::Kind::Eol(o) => txt::DiffOp::Eol { index: to_usize(o.index)?, value: o.value },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiffOp, DocIndex, GenericValue, HunkAction};
+    use crate::generic::{self, from_json};
+    use crate::{diff, txt, MismatchDoc, Mismatches};
+
+    // no wildcards, a new variant fails to compile here until it has the round trip case below
+    fn doc_index_kind(v: &generic::DocIndex) -> usize {
+        match v { generic::DocIndex::Name(_) => 0, generic::DocIndex::Idx(_) => 1 }
+    }
+
+    fn hunk_action_kind(v: &generic::HunkAction) -> usize {
+        match v {
+            generic::HunkAction::Remove => 0,
+            generic::HunkAction::Update(_) => 1,
+            generic::HunkAction::UpdateTxt(_) => 2,
+            generic::HunkAction::Insert(_) => 3,
+            generic::HunkAction::Swap(_) => 4,
+            generic::HunkAction::Clone(_) => 5,
+            generic::HunkAction::Move(_) => 6,
+        }
+    }
+
+    fn generic_value_kind(v: &generic::GenericValue) -> usize {
+        match v {
+            generic::GenericValue::Numeric(_) => 0,
+            generic::GenericValue::Map(_) => 1,
+            generic::GenericValue::Array(_) => 2,
+            generic::GenericValue::Boolean(_) => 3,
+            generic::GenericValue::StringValue(_) => 4,
+            generic::GenericValue::Null => 5,
+        }
+    }
+
+    fn diff_op_kind(v: &txt::DiffOp) -> usize {
+        match v {
+            txt::DiffOp::Remove { .. } => 0,
+            txt::DiffOp::Insert { .. } => 1,
+            txt::DiffOp::Update { .. } => 2,
+            txt::DiffOp::Append { .. } => 3,
+            txt::DiffOp::Delete { .. } => 4,
+            txt::DiffOp::Replace { .. } => 5,
+            txt::DiffOp::Eol { .. } => 6,
+        }
+    }
+
+    fn mismatches_kind(v: &Mismatches) -> usize {
+        match v {
+            #[cfg(feature="patch")]
+            Mismatches::Patch(_) => 0,
+            Mismatches::Doc(_) => 1,
+            Mismatches::Text(_) => 2,
+        }
+    }
+
+    /// all kinds are covered and every value comes back the same
+    fn assert_lossless<T, P>(values: &[T], kind: fn(&T) -> usize, count: usize)
+    where P: for<'a> From<&'a T>, T: PartialEq + std::fmt::Debug + TryFrom<P>, <T as TryFrom<P>>::Error: std::fmt::Debug {
+        let mut kinds: Vec<usize> = values.iter().map(kind).collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        assert_eq!(kinds.len(), count, "{:?}", values);
+        for v in values {
+            assert_eq!(&T::try_from(P::from(v)).unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn test_variants_lossless() {
+        let ops = vec![
+            txt::DiffOp::Remove { index: 3 },
+            txt::DiffOp::Insert { index: 0, value: "".into() },
+            txt::DiffOp::Update { index: 1, value: "ü x".into() },
+            txt::DiffOp::Append { index: 2, pos: 5, value: "!".into() },
+            txt::DiffOp::Delete { index: 2, pos: 0, len: 7 },
+            txt::DiffOp::Replace { index: 4, pos: 1, len: 2, value: "ab".into() },
+            txt::DiffOp::Eol { index: 0, value: "\r\n".into() },
+        ];
+        assert_lossless::<_, DiffOp>(&ops, diff_op_kind, 7);
+
+        let values: Vec<generic::GenericValue> = [r#"1.50"#, r#"{"b": 1, "a": [null]}"#, r#"[true, "x", {}]"#, "false", r#""""#, "null"]
+            .iter().map(|j| from_json(j).unwrap()).collect();
+        assert_lossless::<_, GenericValue>(&values, generic_value_kind, 6);
+
+        let indexes = vec![generic::DocIndex::Name("".into()), generic::DocIndex::Idx(usize::MAX)];
+        assert_lossless::<_, DocIndex>(&indexes, doc_index_kind, 2);
+
+        let actions = vec![
+            generic::HunkAction::Remove,
+            generic::HunkAction::Update(values[1].clone()),
+            generic::HunkAction::UpdateTxt(ops.clone()),
+            generic::HunkAction::Insert(values[5].clone()),
+            generic::HunkAction::Swap(indexes[1].clone()),
+            generic::HunkAction::Clone(indexes[0].clone()),
+            generic::HunkAction::Move(generic::DocIndex::Name("k".into())),
+        ];
+        assert_lossless::<_, HunkAction>(&actions, hunk_action_kind, 7);
+
+        let base = from_json(r#"{"a": 1.50, "b": [true, null, "x"], "c": {"d": "line\nline"}, "e": "f"}"#).unwrap();
+        let input = from_json(r#"{"a": 2, "b": [false, null], "c": {"d": "line\r\nline2", "g": {}}}"#).unwrap();
+        let all = vec![
+            Mismatches::Doc(diff::Mismatch::new(&base, &input).unwrap()),
+            Mismatches::Text(txt::Mismatch(ops)),
+        ];
+        #[cfg(feature="patch")]
+        let all: Vec<_> = all.into_iter()
+            .chain([Mismatches::Patch(crate::patch::Mismatch::new(&"a\nb\nc\n".to_string(), &"a\nc\nd".to_string()).unwrap())]).collect();
+        let count = all.len();
+        let mut kinds: Vec<usize> = all.iter().map(mismatches_kind).collect();
+        kinds.dedup();
+        assert_eq!(kinds.len(), count);
+        for m in all {
+            assert_eq!(Mismatches::from_bytes(&m.to_bytes()).unwrap(), m);
+        }
+    }
+
+    #[cfg(feature="preserve_order")]
+    #[test]
+    fn test_map_keys_order() {
+        let v = from_json(r#"{"z": 1, "a": 2, "m": {"y": 3, "b": 4}}"#).unwrap();
+        let p = GenericValue::from(&v);
+        let Some(super::generic_value::Kind::Map(m)) = &p.kind else { panic!("{:?}", p) };
+        assert_eq!(m.fields.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(), vec!["z", "a", "m"]);
+        let back = generic::GenericValue::try_from(p).unwrap();
+        assert_eq!(crate::generic::to_json(&back).unwrap().replace([' ', '\n'], ""), r#"{"z":1,"a":2,"m":{"y":3,"b":4}}"#);
+    }
+
+    #[cfg(feature="patch")]
+    #[test]
+    fn test_patch_unified() {
+        use super::{mismatches, Mismatches as Proto};
+        use prost::Message;
+        let p = crate::patch::Mismatch::new(&"fn a() {\n  1\n}\n".to_string(), &"fn a() {\n  2\n}".to_string()).unwrap();
+        let m = Mismatches::Patch(p);
+        let Some(mismatches::Kind::Unified(u)) = Proto::decode(m.to_bytes().as_slice()).unwrap().kind else { panic!() };
+        assert_eq!((u.hunks.len(), u.hunks[0].old_start, u.hunks[0].old_len, u.hunks[0].new_len), (1, 1, 3, 3));
+        assert_eq!(Mismatches::from_bytes(&m.to_bytes()).unwrap(), m);
+
+        // a text diffy renders otherwise keeps as it is
+        let raw = "--- a\n+++ b\n@@ -1 +1 @@ fn x\n-1\n+2\n".to_string();
+        let m = Mismatches::Patch(crate::patch::Mismatch(raw.clone()));
+        let Some(mismatches::Kind::Patch(t)) = Proto::decode(m.to_bytes().as_slice()).unwrap().kind else { panic!() };
+        assert_eq!(t, raw);
+        assert_eq!(Mismatches::from_bytes(&m.to_bytes()).unwrap(), m);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let base = from_json(r#"{"a": 1.50, "b": [true, null, "x"], "c": {"d": "line\nline"}, "e": "f"}"#).unwrap();
+        let input = from_json(r#"{"a": 2, "b": [false, null], "c": {"d": "line\r\nline2", "g": {}}}"#).unwrap();
+        let m = Mismatches::Doc(diff::Mismatch::new(&base, &input).unwrap());
+        assert_eq!(Mismatches::from_bytes(&m.to_bytes()).unwrap(), m);
+
+        let m = Mismatches::Text(txt::Mismatch::new(&"a\nb c\r\nd".to_string(), &"b x\nd\n".to_string()).unwrap());
+        assert_eq!(Mismatches::from_bytes(&m.to_bytes()).unwrap(), m);
+        assert!(Mismatches::from_bytes(&[0x1a, 0x05]).is_err());
+        assert!(Mismatches::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_bytes_wire_format() {
+        // Text { ops: [Append { index: 1, pos: 4, value: "!" }] }, see DiffOpAppend in proto/diff_doc.proto
+        let m = Mismatches::Text(txt::Mismatch(vec![txt::DiffOp::Append { index: 1, pos: 4, value: "!".into() }]));
+        assert_eq!(m.to_bytes(), vec![0x1a, 0x0b, 0x0a, 0x09, 0x22, 0x07, 0x08, 0x01, 0x10, 0x04, 0x1a, 0x01, b'!']);
+    }
+
+    /// prost-build output for the schema, parsed by protox so no protoc is needed
+    #[test]
+    fn test_generated_types_match_proto() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let fds = protox::compile(["diff_doc.proto"], [format!("{}/proto", root)]).unwrap();
+        let requests = fds.file.into_iter().map(|f| (prost_build::Module::from_protobuf_package_name(f.package()), f)).collect();
+        let modules = prost_build::Config::new().generate(requests).unwrap();
+        let generated = format!("// generated from proto/diff_doc.proto by prost-build, do not edit:\n\
+            // `DIFF_DOC_PROTO_UPDATE=1 cargo test --features proto` rewrites it\n\n{}",
+            modules.values().next().unwrap());
+        let path = format!("{}/src/proto/diff_doc.rs", root);
+        if std::env::var_os("DIFF_DOC_PROTO_UPDATE").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        assert!(std::fs::read_to_string(&path).unwrap() == generated,
+            "src/proto/diff_doc.rs is out of date, run `DIFF_DOC_PROTO_UPDATE=1 cargo test --features proto`");
+    }
+
+    /// the bytes `c/test_diff_apply.c` decodes and applies to the same base, expecting the same result
+    #[test]
+    fn test_c_fixtures() {
+        use generic::{DocIndex, Hunk, HunkAction};
+        use txt::DiffOp;
+        use crate::MismatchDocCow;
+        let text = |ops: Vec<DiffOp>, base: &str, expected: &str| {
+            let m = txt::Mismatch(ops);
+            assert_eq!(m.apply(&base.to_string()).unwrap(), expected);
+            Mismatches::Text(m)
+        };
+        let name = |n: &str| DocIndex::Name(n.to_string());
+        let doc = diff::Mismatch(vec![
+            Hunk { path: vec![name("b"), DocIndex::Idx(2)], value: HunkAction::Insert(from_json("3").unwrap()) },
+            Hunk { path: vec![name("c")], value: HunkAction::Update(from_json(r#""y""#).unwrap()) },
+            Hunk { path: vec![name("c")], value: HunkAction::Move(DocIndex::Idx(0)) },
+        ]);
+        let mut applied = from_json(r#"{"a": 1, "b": [1, 2], "c": "x"}"#).unwrap();
+        crate::MismatchDocMut::apply_tx(&doc, &mut applied).unwrap();
+        assert_eq!(applied, from_json(r#"{"c": "y", "a": 1, "b": [1, 2, 3]}"#).unwrap());
+        #[cfg(feature="preserve_order")]
+        assert_eq!(generic::to_json(&applied).unwrap().replace([' ', '\n'], ""), r#"{"c":"y","a":1,"b":[1,2,3]}"#);
+
+        let fixtures = [
+            ("wire_append", text(vec![DiffOp::Append { index: 1, pos: 4, value: "!".into() }], "a\nline\n", "a\nline!\n")),
+            ("txt_eol", text(vec![
+                DiffOp::Remove { index: 0 },
+                DiffOp::Insert { index: 1, value: "2.5".into() },
+                DiffOp::Update { index: 0, value: "TWO".into() },
+                DiffOp::Eol { index: 0, value: "\n".into() },
+                DiffOp::Eol { index: 2, value: "\n".into() },
+            ], "one\r\ntwo\r\nthree\r\n", "TWO\n2.5\r\nthree\n")),
+            ("doc_move", Mismatches::Doc(doc)),
+        ];
+        for (name, m) in fixtures {
+            let path = format!("{}/c/fixtures/{}.bin", env!("CARGO_MANIFEST_DIR"), name);
+            if std::env::var_os("DIFF_DOC_PROTO_UPDATE").is_some() {
+                std::fs::write(&path, m.to_bytes()).unwrap();
+            }
+            assert!(std::fs::read(&path).unwrap() == m.to_bytes(),
+                "{} is out of date, run `DIFF_DOC_PROTO_UPDATE=1 cargo test --features proto`", path);
+        }
+    }
+}
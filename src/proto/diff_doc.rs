@@ -0,0 +1,290 @@
+// generated from proto/diff_doc.proto by prost-build, do not edit:
+// `DIFF_DOC_PROTO_UPDATE=1 cargo test --features proto` rewrites it
+
+// This file is @generated by prost-build.
+/// Corresponds to enum Mismatches in src/lib.rs
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Mismatches {
+    #[prost(oneof = "mismatches::Kind", tags = "1, 2, 3, 4")]
+    pub kind: ::core::option::Option<mismatches::Kind>,
+}
+/// Nested message and enum types in `Mismatches`.
+pub mod mismatches {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// patch::Mismatch (feature = "patch") — raw GNU patch text
+        #[prost(string, tag = "1")]
+        Patch(::prost::alloc::string::String),
+        /// diff::Mismatch  — structured JSON/document diff
+        #[prost(message, tag = "2")]
+        Doc(super::DocMismatch),
+        /// txt::Mismatch   — line-level text diff
+        #[prost(message, tag = "3")]
+        Text(super::TxtMismatch),
+        /// patch::Mismatch (feature = "patch") — parsed unified diff
+        #[prost(message, tag = "4")]
+        Unified(super::PatchMismatch),
+    }
+}
+/// Corresponds to the unified diff text of patch::Mismatch in src/patch.rs
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PatchMismatch {
+    /// "--- " header
+    #[prost(string, optional, tag = "1")]
+    pub original: ::core::option::Option<::prost::alloc::string::String>,
+    /// "+++ " header
+    #[prost(string, optional, tag = "2")]
+    pub modified: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag = "3")]
+    pub hunks: ::prost::alloc::vec::Vec<PatchHunk>,
+}
+/// "@@ -old_start,old_len +new_start,new_len @@ function_context"
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PatchHunk {
+    #[prost(uint64, tag = "1")]
+    pub old_start: u64,
+    #[prost(uint64, tag = "2")]
+    pub old_len: u64,
+    #[prost(uint64, tag = "3")]
+    pub new_start: u64,
+    #[prost(uint64, tag = "4")]
+    pub new_len: u64,
+    #[prost(string, optional, tag = "5")]
+    pub function_context: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag = "6")]
+    pub lines: ::prost::alloc::vec::Vec<PatchLine>,
+}
+/// the line with its "\n", the last line of a file without the final newline has none
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PatchLine {
+    #[prost(oneof = "patch_line::Kind", tags = "1, 2, 3")]
+    pub kind: ::core::option::Option<patch_line::Kind>,
+}
+/// Nested message and enum types in `PatchLine`.
+pub mod patch_line {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// " "
+        #[prost(string, tag = "1")]
+        Context(::prost::alloc::string::String),
+        /// "-"
+        #[prost(string, tag = "2")]
+        Delete(::prost::alloc::string::String),
+        /// "+"
+        #[prost(string, tag = "3")]
+        Insert(::prost::alloc::string::String),
+    }
+}
+/// Corresponds to diff::Mismatch(Vec<Hunk>) in src/diff.rs
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DocMismatch {
+    #[prost(message, repeated, tag = "1")]
+    pub hunks: ::prost::alloc::vec::Vec<Hunk>,
+}
+/// Corresponds to generic::Hunk in src/generic.rs
+/// serde renames: path → "p", value → "v"
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Hunk {
+    /// "p"
+    #[prost(message, repeated, tag = "1")]
+    pub path: ::prost::alloc::vec::Vec<DocIndex>,
+    /// "v"
+    #[prost(message, optional, tag = "2")]
+    pub value: ::core::option::Option<HunkAction>,
+}
+/// Corresponds to generic::DocIndex in src/generic.rs
+/// serde renames: Name → "n", Idx → "i"
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DocIndex {
+    #[prost(oneof = "doc_index::Kind", tags = "1, 2")]
+    pub kind: ::core::option::Option<doc_index::Kind>,
+}
+/// Nested message and enum types in `DocIndex`.
+pub mod doc_index {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// "n"
+        #[prost(string, tag = "1")]
+        Name(::prost::alloc::string::String),
+        /// "i"
+        #[prost(uint64, tag = "2")]
+        Idx(u64),
+    }
+}
+/// Corresponds to generic::HunkAction in src/generic.rs
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HunkAction {
+    #[prost(oneof = "hunk_action::Kind", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub kind: ::core::option::Option<hunk_action::Kind>,
+}
+/// Nested message and enum types in `HunkAction`.
+pub mod hunk_action {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// Remove (unit variant — set true)
+        #[prost(bool, tag = "1")]
+        Remove(bool),
+        /// Update(GenericValue)
+        #[prost(message, tag = "2")]
+        Update(super::GenericValue),
+        /// UpdateTxt(Vec<DiffOp>)
+        #[prost(message, tag = "3")]
+        UpdateTxt(super::TxtMismatch),
+        /// Insert(GenericValue)
+        #[prost(message, tag = "4")]
+        Insert(super::GenericValue),
+        /// Swap(DocIndex)
+        #[prost(message, tag = "5")]
+        Swap(super::DocIndex),
+        /// Clone(DocIndex)
+        #[prost(message, tag = "6")]
+        Clone(super::DocIndex),
+        /// Move(DocIndex)
+        #[prost(message, tag = "7")]
+        Move(super::DocIndex),
+    }
+}
+/// Corresponds to generic::GenericValue in src/generic.rs (untagged serde)
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenericValue {
+    #[prost(oneof = "generic_value::Kind", tags = "1, 2, 3, 4, 5, 6")]
+    pub kind: ::core::option::Option<generic_value::Kind>,
+}
+/// Nested message and enum types in `GenericValue`.
+pub mod generic_value {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// Numeric(NumericString) — number stored as string
+        #[prost(string, tag = "1")]
+        Numeric(::prost::alloc::string::String),
+        /// Map(HashMap<String, GenericValue>)
+        #[prost(message, tag = "2")]
+        Map(super::GenericMap),
+        /// Array(Vec<GenericValue>)
+        #[prost(message, tag = "3")]
+        Array(super::GenericArray),
+        /// Boolean(bool)
+        #[prost(bool, tag = "4")]
+        Boolean(bool),
+        /// StringValue(String)
+        #[prost(string, tag = "5")]
+        String(::prost::alloc::string::String),
+        /// Null (set true)
+        #[prost(bool, tag = "6")]
+        Null(bool),
+    }
+}
+/// the entries are in the keys order on the wire, same as with `preserve_order` feature;
+/// same wire format as map<string, GenericValue> fields = 1, which prost decodes to an unordered map
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenericMap {
+    #[prost(message, repeated, tag = "1")]
+    pub fields: ::prost::alloc::vec::Vec<GenericMapEntry>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenericMapEntry {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub value: ::core::option::Option<GenericValue>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GenericArray {
+    #[prost(message, repeated, tag = "1")]
+    pub items: ::prost::alloc::vec::Vec<GenericValue>,
+}
+/// Corresponds to txt::Mismatch(Vec<DiffOp>) in src/txt.rs
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TxtMismatch {
+    #[prost(message, repeated, tag = "1")]
+    pub ops: ::prost::alloc::vec::Vec<DiffOp>,
+}
+/// Corresponds to txt::DiffOp in src/txt.rs
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffOp {
+    #[prost(oneof = "diff_op::Kind", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub kind: ::core::option::Option<diff_op::Kind>,
+}
+/// Nested message and enum types in `DiffOp`.
+pub mod diff_op {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Kind {
+        /// Remove { index }
+        #[prost(message, tag = "1")]
+        Remove(super::DiffOpRemove),
+        /// Insert { index, value }
+        #[prost(message, tag = "2")]
+        Insert(super::DiffOpInsert),
+        /// Update { index, value }
+        #[prost(message, tag = "3")]
+        Update(super::DiffOpUpdate),
+        /// Append { index, pos, value }
+        #[prost(message, tag = "4")]
+        Append(super::DiffOpAppend),
+        /// Delete { index, pos, len }
+        #[prost(message, tag = "5")]
+        Delete(super::DiffOpDelete),
+        /// Replace { index, pos, len, value }
+        #[prost(message, tag = "6")]
+        Replace(super::DiffOpReplace),
+        /// Eol { index, value }
+        #[prost(message, tag = "7")]
+        Eol(super::DiffOpEol),
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DiffOpRemove {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffOpInsert {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffOpUpdate {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffOpAppend {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(uint64, tag = "2")]
+    pub pos: u64,
+    #[prost(string, tag = "3")]
+    pub value: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DiffOpDelete {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(uint64, tag = "2")]
+    pub pos: u64,
+    #[prost(uint64, tag = "3")]
+    pub len: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffOpReplace {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(uint64, tag = "2")]
+    pub pos: u64,
+    #[prost(uint64, tag = "3")]
+    pub len: u64,
+    #[prost(string, tag = "4")]
+    pub value: ::prost::alloc::string::String,
+}
+/// value is one of "\n", "\r\n" or "\r"
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffOpEol {
+    #[prost(uint64, tag = "1")]
+    pub index: u64,
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+}
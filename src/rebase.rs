@@ -0,0 +1,232 @@
+use crate::diff::{Conflict, ConflictReason};
+use crate::generic::{DocIndex, Hunk, HunkAction};
+use crate::txt;
+
+/// transform hunks calculated against the base to apply after the other hunks calculated against the same base,
+/// each pair of hunks transformed both ways, so the other hunks follow the changes of the rebased ones
+pub(crate) fn rebase_hunks(input: &[Hunk], onto: &[Hunk]) -> Result<Vec<Hunk>, Conflict> {
+    let mut onto: Vec<Option<Hunk>> = onto.iter().cloned().map(Some).collect();
+    let mut out = Vec::with_capacity(input.len());
+    for (ia, a) in input.iter().enumerate() {
+        let mut a = Some(a.clone());
+        for (ib, b) in onto.iter_mut().enumerate() {
+            if let (Some(x), Some(y)) = (a.as_ref(), b.as_ref()) {
+                let conflict = |reason| {
+                    let path = x.path.iter().zip(y.path.iter())
+                        .take_while(|(p, q)| p == q).map(|(p, _)| p.clone()).collect();
+                    Conflict { hunk_a: ia, hunk_b: ib, path, reason }
+                };
+                let a2 = shift(x, y, true).map_err(conflict)?;
+                let b2 = shift(y, x, false).map_err(conflict)?;
+                a = a2;
+                *b = b2;
+            }
+        }
+        if let Some(a) = a {
+            out.push(a);
+        }
+    }
+    Ok(out)
+}
+
+/// transform the hunk to apply after `by`, both calculated against the same document state,
+/// returns None if `by` already did the same change;
+/// if both insert at the same array index, the `tie_after` hunk goes after the other one
+fn shift(x: &Hunk, by: &Hunk, tie_after: bool) -> Result<Option<Hunk>, ConflictReason> {
+    let lx = x.path.len();
+    let lb = by.path.len();
+    if lx == 0 || lb == 0 {
+        return Ok(Some(x.clone()));
+    }
+    let mut path = x.path.clone();
+    let common = x.path.iter().zip(by.path.iter()).take_while(|(a, b)| a == b).count();
+
+    if common < lx && common < lb {
+        // diverged paths, `by` may shift or move the element at the same container
+        if common + 1 == lb && common + 1 == lx && (is_move(x) || is_move(by)) && !is_txt(x) && !is_txt(by) {
+            return Err(ConflictReason::KeyOrder);
+        }
+        if common + 1 == lb {
+            match (&by.path[common], &mut path[common], &by.value) {
+                (DocIndex::Idx(i), DocIndex::Idx(j), HunkAction::Insert(_) | HunkAction::Clone(_)) if *j > *i => *j += 1,
+                (DocIndex::Idx(i), DocIndex::Idx(j), HunkAction::Remove) if *j > *i => *j -= 1,
+                (DocIndex::Idx(i), DocIndex::Idx(j), HunkAction::Swap(DocIndex::Idx(k))) if *j == *k => *j = *i,
+                (DocIndex::Name(n), DocIndex::Name(j), HunkAction::Swap(DocIndex::Name(k))) if j == k => *j = n.clone(),
+                _ => {}
+            }
+            if matches!(&by.value, HunkAction::Clone(src) if src == &x.path[common]) && is_write(x, common) {
+                return Err(ConflictReason::SameElement); // copy source changed
+            }
+        }
+    } else if common == lb && lb == lx {
+        // same target
+        match &by.path[lb - 1] {
+            DocIndex::Idx(i) => {
+                let i = *i;
+                match (&by.value, &x.value) {
+                    (HunkAction::Insert(b), HunkAction::Insert(a)) if a == b => return Ok(None),
+                    (HunkAction::Insert(_) | HunkAction::Clone(_), HunkAction::Insert(_) | HunkAction::Clone(_)) => {
+                        if tie_after {
+                            path[lb - 1] = DocIndex::Idx(i + 1);
+                        }
+                    }
+                    (HunkAction::Insert(_) | HunkAction::Clone(_), _) => path[lb - 1] = DocIndex::Idx(i + 1),
+                    (_, HunkAction::Insert(_) | HunkAction::Clone(_)) => {}
+                    (HunkAction::Update(_) | HunkAction::UpdateTxt(_), HunkAction::Swap(_)) => {}
+                    (HunkAction::Remove, HunkAction::Remove) => return Ok(None),
+                    (HunkAction::Update(b), HunkAction::Update(a)) if a == b => return Ok(None),
+                    (HunkAction::UpdateTxt(b), HunkAction::UpdateTxt(a)) => {
+                        if a == b {
+                            return Ok(None);
+                        }
+                        return Ok(Some(Hunk { path, value: HunkAction::UpdateTxt(txt_rebase(a, b)?) }));
+                    }
+                    (HunkAction::Swap(DocIndex::Idx(k)), HunkAction::Update(_) | HunkAction::UpdateTxt(_) | HunkAction::Remove) =>
+                        path[lb - 1] = DocIndex::Idx(*k),
+                    (HunkAction::Remove, _) => return Err(ConflictReason::ShiftedIndex),
+                    _ => return Err(ConflictReason::SameElement),
+                }
+            }
+            DocIndex::Name(_) => {
+                match (&by.value, &x.value) {
+                    (HunkAction::Move(b), HunkAction::Move(a)) if a == b => return Ok(None),
+                    (HunkAction::Move(_), HunkAction::UpdateTxt(_)) | (HunkAction::UpdateTxt(_), HunkAction::Move(_)) => {}
+                    (HunkAction::Move(_), _) | (_, HunkAction::Move(_)) => return Err(ConflictReason::KeyOrder),
+                    (HunkAction::Update(b) | HunkAction::Insert(b), HunkAction::Update(a) | HunkAction::Insert(a)) if a == b =>
+                        return Ok(None),
+                    (HunkAction::Remove, HunkAction::Remove) => return Ok(None),
+                    (HunkAction::UpdateTxt(b), HunkAction::UpdateTxt(a)) => {
+                        if a == b {
+                            return Ok(None);
+                        }
+                        return Ok(Some(Hunk { path, value: HunkAction::UpdateTxt(txt_rebase(a, b)?) }));
+                    }
+                    _ => return Err(ConflictReason::DifferentValues),
+                }
+            }
+        }
+    } else if common == lb {
+        // `by` changes an ancestor of the target
+        match (&by.path[lb - 1], &mut path[lb - 1], &by.value) {
+            (DocIndex::Idx(_), DocIndex::Idx(j), HunkAction::Insert(_) | HunkAction::Clone(_)) => *j += 1,
+            (DocIndex::Idx(_), DocIndex::Idx(j), HunkAction::Swap(DocIndex::Idx(k))) => *j = *k,
+            (DocIndex::Name(_), DocIndex::Name(j), HunkAction::Swap(DocIndex::Name(k))) => *j = k.clone(),
+            (DocIndex::Name(_), _, HunkAction::Move(_)) => {}
+            (DocIndex::Idx(_), _, HunkAction::Remove) => return Err(ConflictReason::ShiftedIndex),
+            (DocIndex::Idx(_), _, _) => return Err(ConflictReason::SameElement),
+            _ => return Err(ConflictReason::DifferentValues),
+        }
+    } else {
+        // the target is an ancestor of `by` changes
+        match (&x.path[lx - 1], &x.value) {
+            (DocIndex::Idx(_), HunkAction::Insert(_) | HunkAction::Clone(_) | HunkAction::Swap(_)) => {}
+            (DocIndex::Name(_), HunkAction::Move(_)) => {}
+            (DocIndex::Idx(_), _) => return Err(ConflictReason::SameElement),
+            _ => return Err(ConflictReason::DifferentValues),
+        }
+    }
+
+    let value = shift_action(x, by)?;
+    Ok(Some(Hunk { path, value }))
+}
+
+fn is_move(x: &Hunk) -> bool {
+    matches!(x.value, HunkAction::Move(_))
+}
+
+fn is_txt(x: &Hunk) -> bool {
+    matches!(x.value, HunkAction::UpdateTxt(_))
+}
+
+/// the hunk changes the element at the path depth or below
+fn is_write(x: &Hunk, depth: usize) -> bool {
+    x.path.len() > depth + 1 || !matches!(x.value, HunkAction::Insert(_) | HunkAction::Clone(_))
+}
+
+/// the swap and clone refer to array index of the same container, which `by` may shift
+fn shift_action(x: &Hunk, by: &Hunk) -> Result<HunkAction, ConflictReason> {
+    let lx = x.path.len();
+    let lb = by.path.len();
+    if let (HunkAction::Swap(DocIndex::Idx(k)) | HunkAction::Clone(DocIndex::Idx(k)), DocIndex::Idx(m)) = (&x.value, &by.path[lb - 1])
+        && lx == lb && x.path[..lx - 1] == by.path[..lb - 1] {
+        let (k, m) = (*k, *m);
+        let k = match &by.value {
+            HunkAction::Insert(_) | HunkAction::Clone(_) if k >= m => k + 1,
+            HunkAction::Remove if k == m => return Err(ConflictReason::ShiftedIndex),
+            HunkAction::Remove if k > m => k - 1,
+            HunkAction::Swap(DocIndex::Idx(m2)) if k == m => *m2,
+            HunkAction::Swap(DocIndex::Idx(m2)) if k == *m2 => m,
+            HunkAction::Update(_) | HunkAction::UpdateTxt(_) if k == m && matches!(x.value, HunkAction::Clone(_)) =>
+                return Err(ConflictReason::SameElement),
+            _ => k,
+        };
+        return Ok(if matches!(x.value, HunkAction::Swap(_)) {
+            HunkAction::Swap(DocIndex::Idx(k))
+        } else {
+            HunkAction::Clone(DocIndex::Idx(k))
+        });
+    }
+    Ok(x.value.clone())
+}
+
+/// text changes of the same string apply after the other ones if not intersect,
+/// the changes within the same line shift by the other changes before them
+fn txt_rebase(a: &[txt::DiffOp], b: &[txt::DiffOp]) -> Result<Vec<txt::DiffOp>, ConflictReason> {
+    txt::Mismatch(a.to_vec()).rebase(&txt::Mismatch(b.to_vec()))
+        .map(|m| m.0)
+        .map_err(|_| ConflictReason::SameElement)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{ConflictReason, Mismatch};
+    use crate::generic::{from_json, from_str_vec, GenericValue};
+    use crate::{MismatchDoc, MismatchDocMut};
+
+    fn check(base: &GenericValue, a: &GenericValue, b: &GenericValue, result: &GenericValue) {
+        let pa = Mismatch::new(base, a).unwrap();
+        let pb = Mismatch::new(base, b).unwrap();
+        let rb = pb.rebase(&pa).unwrap();
+        let mut doc = base.clone();
+        pa.apply_tx(&mut doc).unwrap();
+        rb.apply_tx(&mut doc).unwrap();
+        assert_eq!(&doc, result, "\n{:?}\n{:?}\n{:?}", pa, pb, rb);
+
+        let ra = pa.rebase(&pb).unwrap();
+        let mut doc = base.clone();
+        pb.apply_tx(&mut doc).unwrap();
+        ra.apply_tx(&mut doc).unwrap();
+        assert_eq!(&doc, result, "\n{:?}\n{:?}\n{:?}", pb, pa, ra);
+    }
+
+    #[test]
+    fn test_rebase_remove_update() {
+        let base = from_str_vec(vec!["a", "b", "c"]);
+        let a = from_str_vec(vec!["b", "c"]);
+        let b = from_str_vec(vec!["a", "b", "d"]);
+        assert!(Mismatch::new(&base, &a).unwrap().is_intersect(&Mismatch::new(&base, &b).unwrap()).unwrap());
+        check(&base, &a, &b, &from_str_vec(vec!["b", "d"]));
+    }
+
+    #[test]
+    fn test_rebase_nested() {
+        let base = from_json(r#"{"l": [{"id": 1, "v": "a"}, {"id": 2, "v": "b"}, {"id": 3, "v": "c"}]}"#).unwrap();
+        let pa: Mismatch = serde_json::from_str(r#"[{"p": [{"n": "l"}, {"i": 0}], "v": {"Insert": {"id": 0, "v": "z"}}}]"#).unwrap();
+        let pb: Mismatch = serde_json::from_str(r#"[{"p": [{"n": "l"}, {"i": 2}, {"n": "v"}], "v": {"Update": "c2"}},
+            {"p": [{"n": "l"}, {"i": 0}], "v": {"Swap": {"i": 1}}}]"#).unwrap();
+        let rb = pb.rebase(&pa).unwrap();
+        let mut doc = base.clone();
+        pa.apply_tx(&mut doc).unwrap();
+        rb.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, from_json(r#"{"l": [{"id": 0, "v": "z"}, {"id": 2, "v": "b"}, {"id": 1, "v": "a"}, {"id": 3, "v": "c2"}]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_rebase_conflict() {
+        let base = from_str_vec(vec!["a", "b", "c"]);
+        let pa = Mismatch::new(&base, &from_str_vec(vec!["a", "c"])).unwrap();
+        let pb = Mismatch::new(&base, &from_str_vec(vec!["a", "x", "c"])).unwrap();
+        let c = pb.rebase(&pa).unwrap_err();
+        assert_eq!(c.reason, ConflictReason::ShiftedIndex);
+    }
+}
@@ -1,5 +1,7 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-use crate::{DocError, MismatchDoc, MismatchDocCow};
+use crate::{DocError, MismatchDoc, MismatchDocCow, MismatchDocMut};
+use crate::compose::compose_ops;
 
 #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Mismatch(pub Vec<DiffOp>);
@@ -16,6 +18,12 @@ pub enum DiffOp {
     /// Insert `suffix` at byte position `pos` within the line at `index` (no end-of-line allowed).
     /// Multiple `Append` ops may target the same `index`.
     Append { index: usize, pos: usize, value: String },
+    /// Delete `len` bytes at byte position `pos` within the line at `index`.
+    Delete { index: usize, pos: usize, len: usize },
+    /// Replace `len` bytes at byte position `pos` within the line at `index` with `value` (no end-of-line allowed).
+    Replace { index: usize, pos: usize, len: usize, value: String },
+    /// Set the end-of-line after the line at `index` to `value`: `"\n"`, `"\r\n"` or `"\r"`.
+    Eol { index: usize, value: String },
 }
 
 impl DiffOp {
@@ -28,19 +36,55 @@ impl DiffOp {
         }
     }
 
-    fn index(&self) -> usize {
+    pub(crate) fn index(&self) -> usize {
         match &self {
             DiffOp::Remove{index} => *index,
             DiffOp::Insert{index, ..} => *index,
             DiffOp::Update{index, ..} => *index,
             DiffOp::Append{index, ..} => *index,
+            DiffOp::Delete{index, ..} => *index,
+            DiffOp::Replace{index, ..} => *index,
+            DiffOp::Eol{index, ..} => *index,
         }
     }
 
+    /// Returns true if this operation changes a part of the line
+    pub(crate) fn is_inline(&self) -> bool {
+        matches!(self, DiffOp::Append{..} | DiffOp::Delete{..} | DiffOp::Replace{..})
+    }
+
+    /// byte position, replaced length and the new text length of the change within the line
+    fn inline_span(&self) -> Option<(usize, usize, usize)> {
+        match &self {
+            DiffOp::Append{pos, value, ..} => Some((*pos, 0, value.len())),
+            DiffOp::Delete{pos, len, ..} => Some((*pos, *len, 0)),
+            DiffOp::Replace{pos, len, value, ..} => Some((*pos, *len, value.len())),
+            _ => None,
+        }
+    }
+
+    fn with_pos(&self, pos: usize) -> DiffOp {
+        let mut op = self.clone();
+        if let DiffOp::Append{pos: p, ..} | DiffOp::Delete{pos: p, ..} | DiffOp::Replace{pos: p, ..} = &mut op {
+            *p = pos;
+        }
+        op
+    }
+
     /// Returns true if this operation does not match the other by index
-    /// i.e. if same index but different operation type OR update value, returns false
+    /// i.e. if same index but different operation type OR update value, returns false;
+    /// the changes within the same line are checked by `inline_conflict`
     fn unmatch(&self, other: &Self) -> bool {
-        self.index() == other.index() &&
+        match (self, other) {
+            (DiffOp::Eol{index: a_i, value: a_v}, DiffOp::Eol{index: b_i, value: b_v}) => return a_i == b_i && a_v != b_v,
+            // the inserted or removed line takes the end-of-line after it, or before it at the end of the text
+            (DiffOp::Eol{index: k, ..}, DiffOp::Insert{index, ..} | DiffOp::Remove{index}) |
+            (DiffOp::Insert{index, ..} | DiffOp::Remove{index}, DiffOp::Eol{index: k, ..}) => return (*k..=k + 2).contains(index),
+            // the end-of-line commutes with the changes of the line content
+            (DiffOp::Eol{..}, _) | (_, DiffOp::Eol{..}) => return false,
+            _ => {}
+        }
+        self.index() == other.index() && !(self.is_inline() && other.is_inline()) &&
         match &self {
             DiffOp::Remove{..} => true,
             DiffOp::Insert{value: a_v, ..} =>
@@ -53,17 +97,37 @@ impl DiffOp {
                     DiffOp::Update{value: b_v, ..} => a_v != b_v,
                     _ => true,
                 }
-            DiffOp::Append{pos: a_p, value: a_v, ..} =>
-                match other {
-                    DiffOp::Append{pos: b_pos, value: b_v, ..} => a_p == b_pos && a_v != b_v,
-                    _ => true
-                }
+            DiffOp::Append{..} | DiffOp::Delete{..} | DiffOp::Replace{..} | DiffOp::Eol{..} => true,
         }
 
     }
 
 }
 
+/// Unit of the changes within a line, the line is updated as a whole if most of it changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Granularity {
+    /// Replace the whole changed line.
+    Line,
+    /// Change whole words, a word is a run of letters and digits, a run of spaces or any other single character.
+    Word,
+    /// Change any characters.
+    #[default]
+    Char,
+}
+
+/// Matching of the equal lines, all find the longest common subsequence, but may differ in the choice of the lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Algorithm {
+    /// Myers diff in linear space.
+    #[default]
+    Myers,
+    /// Match the lines unique in both texts first, then the gaps between them by Myers diff.
+    Patience,
+    /// Full LCS table, O(n·m) space.
+    Lcs,
+}
+
 /// A modified slice between equal regions.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Chunk {
@@ -82,6 +146,11 @@ impl fmt::Display for DiffOp {
             DiffOp::Append { index, pos, value: suffix } => {
                 write!(f, "Append(index={}, pos={}, suffix={:?})", index, pos, suffix)
             }
+            DiffOp::Delete { index, pos, len } => write!(f, "Delete(index={}, pos={}, len={})", index, pos, len),
+            DiffOp::Replace { index, pos, len, value } => {
+                write!(f, "Replace(index={}, pos={}, len={}, value={:?})", index, pos, len, value)
+            }
+            DiffOp::Eol { index, value } => write!(f, "Eol(index={}, value={:?})", index, value),
         }
     }
 }
@@ -103,6 +172,15 @@ fn lcs_table(a: &Vec<&str>, b: &Vec<&str>) -> Vec<Vec<usize>> {
     dp
 }
 
+/// Matched pairs (indices into a and b) in forward order by the algorithm.
+fn matches(a: &Vec<&str>, b: &Vec<&str>, algorithm: Algorithm) -> Vec<(usize, usize)> {
+    match algorithm {
+        Algorithm::Myers => crate::matching::myers(a, b),
+        Algorithm::Patience => crate::matching::patience(a, b),
+        Algorithm::Lcs => backtrack_matches(a, b, &lcs_table(a, b)),
+    }
+}
+
 /// Backtrack LCS matches into forward-ordered pairs (indices into a and b).
 fn backtrack_matches(a: &Vec<&str>, b: &Vec<&str>, dp: &[Vec<usize>]) -> Vec<(usize, usize)> {
     let mut i = a.len();
@@ -175,62 +253,78 @@ fn _inline_insert_appends_multi(index: usize, old_line: &str, new_line: &str) ->
 }
 
 
-fn inline_insert_appends(index: usize, old_line: &str, new_line: &str) -> Option<Vec<DiffOp>> {
-    if old_line == new_line {
-        return Some(vec![]);
-    }
-    let old_chars: Vec<char> = old_line.chars().collect();
-    let new_chars: Vec<char> = new_line.chars().collect();
-
-    let mut oi = 0usize;
-    let mut inserts: Vec<(usize, String)> = Vec::new();
-    let mut pending = String::new();
-    let mut insert_pos_chars = 0usize;
-
-    for &ch in &new_chars {
-        if oi < old_chars.len() && ch == old_chars[oi] {
-            if !pending.is_empty() {
-                let byte_pos = old_chars[..insert_pos_chars].iter().collect::<String>().len()
-                    + inserts.iter().map(|(_, s)| s.len()).sum::<usize>();
-                inserts.push((byte_pos, pending.clone()));
-                pending.clear();
-            }
-            oi += 1;
-            insert_pos_chars = oi;
-        } else {
-            if pending.is_empty() {
-                insert_pos_chars = oi;
-            }
-            pending.push(ch);
+/// limit of the LCS table size to diff within a line by the LCS algorithm
+const MAX_INLINE_CELLS: usize = 1 << 20;
+
+/// split the line to the units of the changes, the characters or the words
+fn tokens(line: &str, granularity: Granularity) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0usize;
+    let mut prev: Option<char> = None;
+    for (i, ch) in line.char_indices() {
+        let joined = granularity == Granularity::Word && prev.is_some_and(|p|
+            p.is_alphanumeric() && ch.is_alphanumeric() || p.is_whitespace() && ch.is_whitespace());
+        if i > 0 && !joined {
+            out.push(&line[start..i]);
+            start = i;
         }
+        prev = Some(ch);
     }
-    if !pending.is_empty() {
-        let byte_pos = old_chars[..insert_pos_chars].iter().collect::<String>().len()
-            + inserts.iter().map(|(_, s)| s.len()).sum::<usize>();
-        inserts.push((byte_pos, pending));
+    if start < line.len() {
+        out.push(&line[start..]);
     }
+    out
+}
 
-    if oi != old_chars.len() {
+/// changes within the line as token runs of the LCS gaps: Append for insertions, Delete for deletions,
+/// Replace for both; the positions are in the line with the previous changes applied;
+/// None if the line is rather new, i.e. the changed part is not less than half of the old line
+fn inline_edits(index: usize, old_line: &str, new_line: &str, granularity: Granularity, algorithm: Algorithm) -> Option<Vec<DiffOp>> {
+    if old_line == new_line {
+        return Some(vec![]);
+    }
+    if granularity == Granularity::Line {
         return None;
     }
-
-    let total_insert_len: usize = inserts.iter().map(|(_, s)| s.chars().count()).sum();
-    if old_chars.len() > 0 && total_insert_len * 2 >= old_chars.len() {
+    let old_tokens = tokens(old_line, granularity);
+    let new_tokens = tokens(new_line, granularity);
+    if algorithm == Algorithm::Lcs && old_tokens.len().saturating_mul(new_tokens.len()) > MAX_INLINE_CELLS {
         return None;
     }
+    let mut matches = matches(&old_tokens, &new_tokens, algorithm);
+    matches.push((old_tokens.len(), new_tokens.len()));
 
     let mut ops = Vec::new();
-    for (pos, text) in inserts {
-        if text.is_empty() || text.contains('\n') || text.contains('\r') {
+    let mut changed = 0usize;
+    let (mut ai, mut bi, mut pos) = (0usize, 0usize, 0usize);
+    for (am, bm) in matches {
+        let len: usize = old_tokens[ai..am].iter().map(|t| t.len()).sum();
+        let value: String = new_tokens[bi..bm].concat();
+        if value.contains('\n') || value.contains('\r') {
             return None;
         }
-        ops.push(DiffOp::Append { index, pos, value: text });
+        changed += old_tokens[ai..am].iter().map(|t| t.chars().count()).sum::<usize>() + value.chars().count();
+        let new_len = value.len();
+        match (len, new_len) {
+            (0, 0) => {}
+            (0, _) => ops.push(DiffOp::Append { index, pos, value }),
+            (_, 0) => ops.push(DiffOp::Delete { index, pos, len }),
+            _ => ops.push(DiffOp::Replace { index, pos, len, value }),
+        }
+        pos += new_len + old_tokens.get(am).map(|t| t.len()).unwrap_or(0);
+        ai = am + 1;
+        bi = bm + 1;
+    }
+
+    let old_chars = old_line.chars().count();
+    if old_chars > 0 && changed * 2 >= old_chars {
+        return None;
     }
     Some(ops)
 }
 
-fn update_or_append_multi(index: usize, old: &str, new: &str) -> Vec<DiffOp> {
-    if let Some(apps) = inline_insert_appends(index, old, new) {
+fn update_or_append_multi(index: usize, old: &str, new: &str, granularity: Granularity, algorithm: Algorithm) -> Vec<DiffOp> {
+    if let Some(apps) = inline_edits(index, old, new, granularity, algorithm) {
         if !apps.is_empty() {
             apps
         } else {
@@ -247,8 +341,12 @@ fn update_or_append_multi(index: usize, old: &str, new: &str) -> Vec<DiffOp> {
 /// Compute a minimal, stable diff using LCS alignment and pairing gaps into Update/Append/Insert/Remove.
 /// Indices in DiffOp are relative to the evolving vector during application.
 pub fn compute_diff(old: &Vec<&str>, new: &Vec<&str>) -> Vec<DiffOp> {
-    let dp = lcs_table(old, new);
-    let matches = backtrack_matches(old, new, &dp);
+    compute_diff_with(old, new, Granularity::Char, Algorithm::Myers)
+}
+
+/// Compute the diff with the unit of the changes within a line and the matching algorithm of the lines.
+pub fn compute_diff_with(old: &Vec<&str>, new: &Vec<&str>, granularity: Granularity, algorithm: Algorithm) -> Vec<DiffOp> {
+    let matches = matches(old, new, algorithm);
 
     let mut ops = Vec::new();
     let mut ai = 0usize; // pointer in old
@@ -264,7 +362,7 @@ pub fn compute_diff(old: &Vec<&str>, new: &Vec<&str>) -> Vec<DiffOp> {
         let paired = rem_count.min(ins_count);
         for k in 0..paired {
             let idx = cursor + k;
-            let chunk = update_or_append_multi(idx, &old[ai + k], &new[bi + k]);
+            let chunk = update_or_append_multi(idx, old[ai + k], new[bi + k], granularity, algorithm);
             ops.extend(chunk);
         }
         if rem_count > ins_count {
@@ -294,7 +392,7 @@ pub fn compute_diff(old: &Vec<&str>, new: &Vec<&str>) -> Vec<DiffOp> {
     let paired = rem_count.min(ins_count);
     for k in 0..paired {
         let idx = cursor + k;
-        let chunk = update_or_append_multi(idx, &old[ai + k], &new[bi + k]);
+        let chunk = update_or_append_multi(idx, old[ai + k], new[bi + k], granularity, algorithm);
         ops.extend(chunk);
     }
     if rem_count > ins_count {
@@ -383,63 +481,204 @@ fn apply_diff2<'a>(old: &Vec<&'a str>, target: &Vec<&str>, ops: &'a Vec<DiffOp>)
     Ok(out)
 }
 
+/// The lines of the text with the end-of-line after each line but the last one,
+/// so the text ending with the end-of-line has the empty last line.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Lines {
+    pub(crate) lines: Vec<String>,
+    eols: Vec<&'static str>,
+}
+
+const EOLS: [&str; 3] = ["\n", "\r\n", "\r"];
+
+impl Lines {
+    /// split by `"\n"`, `"\r\n"` and the lone `"\r"`
+    pub(crate) fn parse(input: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut eols = Vec::new();
+        let mut start = 0;
+        let bytes = input.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let eol = match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => EOLS[1],
+                b'\r' => EOLS[2],
+                b'\n' => EOLS[0],
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            lines.push(input[start..i].to_string());
+            eols.push(eol);
+            i += eol.len();
+            start = i;
+        }
+        lines.push(input[start..].to_string());
+        Lines { lines, eols }
+    }
+
+    #[cfg(test)]
+    fn from_lines(lines: &[&str]) -> Self {
+        Lines { lines: lines.iter().map(|s| s.to_string()).collect(), eols: vec![EOLS[0]; lines.len().saturating_sub(1)] }
+    }
+
+    pub(crate) fn join(&self) -> String {
+        let mut out = String::with_capacity(self.lines.iter().map(|l| l.len() + 2).sum());
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                out.push_str(self.eols[i - 1]);
+            }
+            out.push_str(line);
+        }
+        out
+    }
+
+    /// the end-of-line of the line inserted at the index, same as of the neighbour lines
+    fn eol_at(&self, index: usize) -> &'static str {
+        self.eols.get(index).or_else(|| index.checked_sub(1).and_then(|i| self.eols.get(i)))
+            .or(self.eols.last()).copied().unwrap_or(EOLS[0])
+    }
+}
+
 /// Apply the diff ops to `old`, returning the transformed Vec<String>.
 /// Prints inputs, diff, and output (prints MUST be in apply).
+#[cfg(test)]
 pub (crate) fn apply_diff(old: &Vec<&str>, ops: &Vec<DiffOp>) -> Result<Vec<String>, String> {
-    // convert old to Vec<String> for mutability
-    let mut out = old.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let mut out = Lines::from_lines(old);
     for op in ops {
-        match op {
-            DiffOp::Remove { index } => {
-                if *index >= out.len() {
-                    return Err(format!("Remove index {} out of bounds {}", index, out.len()));
-                }
-                out.remove(*index);
+        apply_op(&mut out, op)?;
+    }
+
+    Ok(out.lines)
+}
+
+/// Apply single diff op to the lines, returns the ops to revert the change.
+fn apply_op(text: &mut Lines, op: &DiffOp) -> Result<Vec<DiffOp>, String> {
+    let out = &mut text.lines;
+    let revert = match op {
+        DiffOp::Remove { index } => {
+            let n = out.len();
+            if *index >= n {
+                return Err(format!("Remove index {} out of bounds {}", index, n));
             }
-            DiffOp::Insert { index, value } => {
-                if *index > out.len() {
-                    return Err(format!("Insert index {} out of bounds {}", index, out.len()));
-                }
-                out.insert(*index, value.clone());
+            let value = out.remove(*index);
+            // the end-of-line after the line, or before the last one
+            let eol = if index + 1 < n { Some((*index, text.eols.remove(*index))) }
+                else if *index > 0 { Some((index - 1, text.eols.remove(index - 1))) }
+                else { None };
+            let mut revert = vec![DiffOp::Insert { index: *index, value }];
+            if let Some((at, eol)) = eol.filter(|(_, e)| *e != text.eol_at(*index)) {
+                revert.push(DiffOp::Eol { index: at, value: eol.to_string() });
             }
-            DiffOp::Update { index, value } => {
-                if *index >= out.len() {
-                    return Err(format!("Update index {} out of bounds {}", index, out.len()));
-                }
-                out[*index] = value.clone();
+            revert
+        }
+        DiffOp::Insert { index, value } => {
+            if *index > out.len() {
+                return Err(format!("Insert index {} out of bounds {}", index, out.len()));
             }
-            DiffOp::Append { index, pos, value: suffix } => {
-                if *index >= out.len() {
-                    return Err(format!("Append index {} out of bounds {}", index, out.len()));
-                }
-                // pos is a byte index into the current line; we assume prior appends have been applied in-order.
-                if *pos > out[*index].len() {
-                    return Err(format!(
-                        "Append pos {} out of bounds in line {} (len {})",
-                        pos,
-                        index,
-                        out[*index].len()
-                    ));
-                }
-                if suffix.contains('\n') || suffix.contains('\r') {
-                    return Err("Append suffix contains end-of-line".to_string());
-                }
-                out[*index].insert_str(*pos, suffix);
+            if value.contains('\n') || value.contains('\r') {
+                return Err("Insert value contains end-of-line".to_string());
+            }
+            if !out.is_empty() {
+                let eol = text.eol_at(*index);
+                text.eols.insert((*index).min(text.eols.len()), eol);
             }
+            text.lines.insert(*index, value.clone());
+            vec![DiffOp::Remove { index: *index }]
         }
-    }
+        DiffOp::Update { index, value } => {
+            if *index >= out.len() {
+                return Err(format!("Update index {} out of bounds {}", index, out.len()));
+            }
+            if value.contains('\n') || value.contains('\r') {
+                return Err("Update value contains end-of-line".to_string());
+            }
+            vec![DiffOp::Update { index: *index, value: std::mem::replace(&mut out[*index], value.clone()) }]
+        }
+        DiffOp::Append { index, pos, value: suffix } => {
+            if *index >= out.len() {
+                return Err(format!("Append index {} out of bounds {}", index, out.len()));
+            }
+            // pos is a byte index into the current line; we assume prior appends have been applied in-order.
+            if *pos > out[*index].len() {
+                return Err(format!(
+                    "Append pos {} out of bounds in line {} (len {})",
+                    pos,
+                    index,
+                    out[*index].len()
+                ));
+            }
+            if suffix.contains('\n') || suffix.contains('\r') {
+                return Err("Append suffix contains end-of-line".to_string());
+            }
+            let line = out[*index].clone();
+            out[*index].insert_str(*pos, suffix);
+            vec![DiffOp::Update { index: *index, value: line }]
+        }
+        DiffOp::Delete { index, pos, len } => {
+            vec![replace_range(out, *index, *pos, *len, "")?]
+        }
+        DiffOp::Replace { index, pos, len, value } => {
+            vec![replace_range(out, *index, *pos, *len, value)?]
+        }
+        DiffOp::Eol { index, value } => {
+            let eol = EOLS.iter().find(|e| *e == value)
+                .ok_or_else(|| format!("Eol value {:?} is not an end-of-line", value))?;
+            let old = text.eols.get_mut(*index)
+                .ok_or_else(|| format!("Eol index {} out of bounds {}", index, text.lines.len() - 1))?;
+            vec![DiffOp::Eol { index: *index, value: std::mem::replace(old, eol).to_string() }]
+        }
+    };
+    Ok(revert)
+}
 
-    Ok(out)
+/// replace the bytes of the line, returns the op to revert the change
+fn replace_range(out: &mut [String], index: usize, pos: usize, len: usize, value: &str) -> Result<DiffOp, String> {
+    let line = out.get_mut(index).ok_or_else(|| format!("Replace index {} out of bounds", index))?;
+    let end = pos.checked_add(len).filter(|e| *e <= line.len() && line.is_char_boundary(pos) && line.is_char_boundary(*e))
+        .ok_or_else(|| format!("Replace range {}..{} out of bounds in line {} (len {})", pos, pos + len, index, line.len()))?;
+    if value.contains('\n') || value.contains('\r') {
+        return Err("Replace value contains end-of-line".to_string());
+    }
+    let old = std::mem::replace(line, format!("{}{}{}", &line[..pos], value, &line[end..]));
+    Ok(DiffOp::Update { index, value: old })
 }
 
 impl MismatchDocCow<String> for Mismatch {
     fn apply(&self, input: &String) -> Result<String, DocError> {
-        let a: Vec<&str> = input.split("\n").collect();
-        apply_diff(&a, &self.0)
-            .map_err(|e| DocError::new(e))
-            .map(|v| v.join("\n"))
+        let mut out = Lines::parse(input);
+        for op in &self.0 {
+            apply_op(&mut out, op).map_err(DocError::new)?;
+        }
+        Ok(out.join())
+    }
+
+}
+
+impl MismatchDocMut<String> for Mismatch {
+    fn apply_mut(&self, input: &mut String, fail_fast: bool) -> Result<Vec<DocError>, DocError> {
+        let mut out = Lines::parse(input);
+        let mut err = Vec::new();
+        for op in &self.0 {
+            if let Err(e) = apply_op(&mut out, op) {
+                if fail_fast {
+                    *input = out.join();
+                    return Err(DocError::new(e));
+                } else {
+                    err.push(DocError::new(e));
+                }
+            }
+        }
+        *input = out.join();
+        Ok(err)
     }
 
+    /// the lines are the working copy, so the input modified on success only
+    fn apply_tx(&self, input: &mut String) -> Result<(), DocError> {
+        *input = self.apply(input)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -497,10 +736,74 @@ impl Range {
 
 }
 
-pub(crate) fn diff(base: &String, input: &String) -> Vec<DiffOp> {
-    let base: Vec<&str> = base.split("\n").collect();
-    let a: Vec<&str> = input.split("\n").collect();
-    compute_diff(&base, &a)
+/// change within a line as the base byte range with the length difference of the new text
+type Edit = (usize, usize, isize);
+
+/// base position of the change at the line with the previous changes applied,
+/// None if the change is within the new text of a previous change
+fn to_base(edits: &[Edit], pos: usize, len: usize) -> Option<usize> {
+    let mut shift = 0isize;
+    for (start, end, delta) in edits {
+        if pos + len <= (*start as isize + shift) as usize {
+            break;
+        }
+        if pos as isize >= *end as isize + shift + delta {
+            shift += delta;
+        } else {
+            return None;
+        }
+    }
+    usize::try_from(pos as isize - shift).ok()
+}
+
+/// changes within the line at the index in the base positions sorted by the start
+fn line_edits(ops: &[DiffOp], index: usize) -> Option<Vec<Edit>> {
+    let mut edits: Vec<Edit> = Vec::new();
+    for (pos, len, new_len) in ops.iter().filter(|op| op.index() == index).filter_map(|op| op.inline_span()) {
+        let base = to_base(&edits, pos, len)?;
+        edits.insert(edits.partition_point(|e| e.0 <= base), (base, base + len, new_len as isize - len as isize));
+    }
+    Some(edits)
+}
+
+/// both patches change the same line within it at touching or overlapped ranges;
+/// if commute required, the changes before the other ones must keep the length to apply in any order
+fn inline_conflict(a: &[DiffOp], b: &[DiffOp], commute: bool) -> bool {
+    let lines: HashSet<usize> = a.iter().filter(|op| op.is_inline()).map(|op| op.index()).collect();
+    let shifts = |x: &[Edit], y: &[Edit]| x.iter().any(|e| y.iter().filter(|o| o.1 <= e.0).map(|o| o.2).sum::<isize>() != 0);
+    b.iter().filter(|op| op.is_inline() && lines.contains(&op.index())).any(|op| {
+        match (line_edits(a, op.index()), line_edits(b, op.index())) {
+            (Some(ea), Some(eb)) => ea.iter().any(|x| eb.iter().any(|y| x.0 <= y.1 && y.0 <= x.1))
+                || commute && (shifts(&ea, &eb) || shifts(&eb, &ea)),
+            _ => true,
+        }
+    })
+}
+
+/// diff of the line contents, then the end-of-line changes of the lines in the patched text
+pub(crate) fn diff(base: &str, input: &str, granularity: Granularity, algorithm: Algorithm) -> Vec<DiffOp> {
+    let base = Lines::parse(base);
+    let input = Lines::parse(input);
+    let a: Vec<&str> = base.lines.iter().map(|s| s.as_str()).collect();
+    let b: Vec<&str> = input.lines.iter().map(|s| s.as_str()).collect();
+    let mut ops = compute_diff_with(&a, &b, granularity, algorithm);
+    push_eols(&base, &input, &mut ops);
+    ops
+}
+
+/// add the end-of-line changes of the input after the line changes of the base,
+/// false and the ops stay if they do not give the lines of the input
+pub(crate) fn push_eols(base: &Lines, input: &Lines, ops: &mut Vec<DiffOp>) -> bool {
+    let mut out = base.clone();
+    if !ops.iter().all(|op| apply_op(&mut out, op).is_ok()) || out.lines != input.lines {
+        return false;
+    }
+    for (index, (e, t)) in out.eols.iter().zip(&input.eols).enumerate() {
+        if e != t {
+            ops.push(DiffOp::Eol { index, value: t.to_string() });
+        }
+    }
+    true
 }
 
 
@@ -508,34 +811,92 @@ impl MismatchDoc<String> for Mismatch {
     fn new(base: &String, input: &String) -> Result<Self, DocError>
     where
         Self: Sized {
-        Ok(Mismatch(diff(base, input)))
+        Ok(Mismatch(diff(base, input, Granularity::default(), Algorithm::default())))
     }
 
     fn is_intersect(&self, other: &Self) -> Result<bool, DocError> {
+        Ok(self.intersect_lines(other) || inline_conflict(&self.0, &other.0, true))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Mismatch {
+    /// calculate the patch with the unit of the changes within a line and the matching algorithm of the lines
+    pub fn new_with(base: &String, input: &String, granularity: Granularity, algorithm: Algorithm) -> Result<Self, DocError> {
+        Ok(Mismatch(diff(base, input, granularity, algorithm)))
+    }
+
+    /// transform the patch calculated against the base to apply after the other patch calculated against the same base,
+    /// the changes within the same line shift by the changes of the other patch before them;
+    /// apply(rebase(p_b, p_a), apply(p_a, base)) contains changes of both patches
+    pub fn rebase(&self, onto: &Mismatch) -> Result<Mismatch, DocError> {
+        if self.intersect_lines(onto) || inline_conflict(&self.0, &onto.0, false) {
+            return Err(DocError::new("the patches change the same lines"));
+        }
+        let mut own: HashMap<usize, Vec<Edit>> = HashMap::new();
+        let mut out = Vec::with_capacity(self.0.len());
+        for op in &self.0 {
+            match op.inline_span() {
+                Some((pos, len, new_len)) => {
+                    let edits = own.entry(op.index()).or_default();
+                    let base = to_base(edits, pos, len)
+                        .ok_or_else(|| DocError::new(format!("the change overlaps the previous one: {}", op)))?;
+                    let shift: isize = line_edits(&onto.0, op.index()).unwrap_or_default().iter()
+                        .filter(|e| e.1 <= base).map(|e| e.2).sum();
+                    edits.insert(edits.partition_point(|e| e.0 <= base), (base, base + len, new_len as isize - len as isize));
+                    out.push(op.with_pos((pos as isize + shift) as usize));
+                }
+                None => out.push(op.clone()),
+            }
+        }
+        Ok(Mismatch(out))
+    }
+
+    /// the line ranges of inserts or removes overlap or the same line changed by both
+    fn intersect_lines(&self, other: &Self) -> bool {
         let ranges = Range::new(&other.0);
         for r1 in &Range::new(&self.0) {
             for r2 in &ranges {
                 if r1.overlap(r2) {
-                    return Ok(true);
+                    return true;
                 }
             }
         }
 
-        // there is no lines to delete (None value) and update after
+        // there is no lines to delete (None value) and update after, by any op of the other patch
         for k in &self.0 {
-            if other.0.iter().find_map(|v| Some(v.unmatch(k))).unwrap_or(false) {
-                return Ok(true);
+            if other.0.iter().any(|v| v.unmatch(k)) {
+                return true;
             }
         }
-        Ok(false)
+        false
     }
 
-    fn len(&self) -> usize {
-        self.0.len()
+    /// reverse patch to restore the base from the patched text:
+    /// apply(invert(p), apply(p, base)) == base
+    pub fn invert(&self, base: &str) -> Result<Mismatch, DocError> {
+        self.apply_invert(base).map(|(_, m)| m)
+    }
+
+    /// squash sequential patches, where the second calculated against the base with the first applied:
+    /// apply(compose(p1, p2), base) == apply(p2, apply(p1, base))
+    pub fn compose(first: &Mismatch, second: &Mismatch) -> Mismatch {
+        Mismatch(compose_ops(&first.0, &second.0))
+    }
+
+    /// apply to the input and return the result with the reverse patch
+    pub(crate) fn apply_invert(&self, input: &str) -> Result<(String, Mismatch), DocError> {
+        let mut out = Lines::parse(input);
+        let mut revert = Vec::with_capacity(self.0.len());
+        for op in &self.0 {
+            revert.push(apply_op(&mut out, op).map_err(DocError::new)?);
+        }
+        Ok((out.join(), Mismatch(revert.into_iter().rev().flatten().collect())))
     }
-}
 
-impl Mismatch {
     fn _min2delete(&self) -> Option<usize> {
         self.0.iter()
             .filter(| v| v._is_delete_insert())
@@ -669,6 +1030,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn granularity_of_appends() {
+        let old = vec!["the colour of the sky"];
+        let new = vec!["the colours of the blue sky"];
+        let ops = compute_diff_with(&old, &new, Granularity::Char, Algorithm::Myers);
+        assert_eq!(ops, vec![DiffOp::Append { index: 0, pos: 10, value: "s".into() }, DiffOp::Append { index: 0, pos: 17, value: "e blu".into() }]);
+        let ops = compute_diff_with(&old, &new, Granularity::Word, Algorithm::Myers);
+        assert_eq!(ops, vec![DiffOp::Update { index: 0, value: new[0].into() }]);
+        let new = vec!["the colour of the blue sky"];
+        let ops = compute_diff_with(&old, &new, Granularity::Word, Algorithm::Myers);
+        assert_eq!(ops, vec![DiffOp::Append { index: 0, pos: 18, value: "blue ".into() }]);
+        assert_eq!(apply_diff2(&old, &new, &ops).unwrap(), new);
+        let ops = compute_diff_with(&old, &new, Granularity::Line, Algorithm::Myers);
+        assert_eq!(ops, vec![DiffOp::Update { index: 0, value: new[0].into() }]);
+    }
+
+    #[test]
+    fn inline_delete_replace_and_merge() {
+        let base = "Teh quick brown fox jumps ovr the lazy dog, and teh end".to_string();
+        let a = "The very quick brown fox jumps ovr the lazy dog, and teh end".to_string();
+        let b = "Teh quick brown fox jumps over the lazy dog, and the end".to_string();
+        let pa = Mismatch::new_with(&base, &a, Granularity::Char, Algorithm::Myers).unwrap();
+        let pb = Mismatch::new_with(&base, &b, Granularity::Char, Algorithm::Myers).unwrap();
+        assert!(pa.0.iter().chain(pb.0.iter()).all(|op| op.is_inline()), "{:?} {:?}", pa, pb);
+        assert!(pb.is_intersect(&pa).unwrap());
+        let result = "The very quick brown fox jumps over the lazy dog, and the end";
+        assert_eq!(pb.rebase(&pa).unwrap().apply(&pa.apply(&base).unwrap()).unwrap(), result);
+        assert_eq!(pa.rebase(&pb).unwrap().apply(&pb.apply(&base).unwrap()).unwrap(), result);
+
+        // same length changes apply in any order
+        let c = "Teh quick brown fox jumps ovr the lazy cat, and teh end".to_string();
+        let pa = Mismatch::new_with(&base, &"The quick brown fox jumps ovr the lazy dog, and teh end".to_string(), Granularity::Char, Algorithm::Myers).unwrap();
+        let pc = Mismatch::new_with(&base, &c, Granularity::Word, Algorithm::Myers).unwrap();
+        assert_eq!(pc.0, vec![DiffOp::Replace { index: 0, pos: 39, len: 3, value: "cat".into() }]);
+        assert!(!pa.is_intersect(&pc).unwrap());
+        assert_eq!(pc.apply(&pa.apply(&base).unwrap()).unwrap(), pa.apply(&pc.apply(&base).unwrap()).unwrap());
+
+        let d = "Teh quick brown fox jumps ovr the lazy dog, and teh".to_string();
+        let pd = Mismatch::new_with(&base, &d, Granularity::Word, Algorithm::Myers).unwrap();
+        assert_eq!(pd.0, vec![DiffOp::Delete { index: 0, pos: 51, len: 4 }]);
+        assert!(pd.rebase(&pb).is_err());
+        assert_eq!(pd.invert(&base).unwrap().apply(&d).unwrap(), base);
+    }
+
     #[test]
     fn append_pos_bounds_and_no_eol() {
         let old = vec!["abc".into()];
@@ -719,6 +1124,74 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_apply_tx() {
+        let base = "a\nb\nc".to_string();
+        let m = Mismatch(vec![
+            DiffOp::Remove { index: 0 },
+            DiffOp::Update { index: 5, value: "x".into() },
+        ]);
+        let mut doc = base.clone();
+        assert!(m.apply_tx(&mut doc).is_err());
+        assert_eq!(doc, base);
+
+        let errs = m.apply_mut(&mut doc, false).unwrap();
+        assert_eq!(errs.len(), 1);
+        assert_eq!(doc, "b\nc");
+    }
+
+    #[test]
+    fn test_invert() {
+        let base = "alpha\nbeta\ncar\ndo\nkeep\nzap".to_string();
+        let input = "pre\nalpha\nbetaX\ncar\ndoge\nnewline\nkeep".to_string();
+        let m = Mismatch::new(&base, &input).unwrap();
+        let patched = m.apply(&base).unwrap();
+        assert_eq!(patched, input);
+        let inv = m.invert(&base).unwrap();
+        assert_eq!(inv.apply(&patched).unwrap(), base);
+    }
+
+    #[test]
+    fn line_endings_byte_exact() {
+        let base = "one\r\ntwo\r\nthree\r\n".to_string();
+        let input = "one\r\n2\r\nthree\r\nfour\r\n".to_string();
+        let m = Mismatch::new(&base, &input).unwrap();
+        assert!(m.0.iter().all(|op| !matches!(op, DiffOp::Eol { .. })), "{:?}", m);
+        assert_eq!(m.apply(&base).unwrap(), input);
+
+        // final end-of-line, lone \r and the end-of-line style changes
+        for (a, b) in [("a\nb", "a\nb\n"), ("a\nb\n", "a\nb"), ("a\rb\r", "a\r\nb\nc"), ("a\r\nb", "a\nb"), ("", "\r\n")] {
+            let (a, b) = (a.to_string(), b.to_string());
+            let m = Mismatch::new(&a, &b).unwrap();
+            assert_eq!(m.apply(&a).unwrap(), b);
+            assert_eq!(m.invert(&a).unwrap().apply(&b).unwrap(), a);
+        }
+        let m = Mismatch::new(&"a\r\nb".to_string(), &"a\nb".to_string()).unwrap();
+        assert_eq!(m.0, vec![DiffOp::Eol { index: 0, value: "\n".into() }]);
+
+        // the end-of-line commutes with the change of the line
+        let base = "x\r\ny\r\nz".to_string();
+        let pa = Mismatch::new(&base, &"x\ny\r\nz".to_string()).unwrap();
+        let pb = Mismatch::new(&base, &"x2\r\ny\r\nz".to_string()).unwrap();
+        assert!(!pa.is_intersect(&pb).unwrap());
+        assert_eq!(pb.apply(&pa.apply(&base).unwrap()).unwrap(), "x2\ny\r\nz");
+        let pc = Mismatch::new(&base, &"x\ry\r\nz".to_string()).unwrap();
+        assert!(pa.is_intersect(&pc).unwrap());
+        assert!(Mismatch(vec![DiffOp::Eol { index: 2, value: "\n".into() }]).apply(&base).is_err());
+    }
+
+    /// every op of the other patch is checked, not the first one only
+    #[test]
+    fn test_intersect_not_first_op() {
+        let base = "a\nb\nc".to_string();
+        let pa = Mismatch::new(&base, &"a\nb\nC1".to_string()).unwrap();
+        let pb = Mismatch::new(&base, &"A\nb\nC2".to_string()).unwrap();
+        assert_eq!(pb.0.len(), 2, "{:?}", pb);
+        assert!(pa.is_intersect(&pb).unwrap());
+        assert!(pb.is_intersect(&pa).unwrap());
+        assert!(!pa.is_intersect(&Mismatch::new(&base, &"A\nb\nC1".to_string()).unwrap()).unwrap());
+    }
+
     #[test]
     fn test_ranges() {
         // test range overlap
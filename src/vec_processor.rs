@@ -1,9 +1,10 @@
 use std::cell::RefCell;
-use crate::generic::{hs, DocIndex, GenericValue, Hunk, HunkAction};
-use std::collections::HashMap;
+use crate::generic::{canonical, hs_with, DocIndex, GenericValue, Hunk, HunkAction};
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::rc::{Rc, Weak};
 use crate::map_processor::min_map_changes;
+use crate::diff::DiffOptions;
 
 #[derive(Clone, Debug)]
 struct Idx {
@@ -44,14 +45,19 @@ fn find_target(src: &HashMap<u64, Vec<usize>>, hashs: &Vec<Rc<RefCell<Idx>>>, wo
 
 /// identify minimum changes of array elements from old to new with remove, swap, clone, update and insert operations
 /// operations as Hunk list are based on context path and use append_path to create full path
-pub fn compute_vec_diff(old: &Vec<GenericValue>, new: &Vec<GenericValue>, context_path: &Vec<DocIndex>) -> Vec<Hunk> {
+/// elements match by the identity field if the options have a key for the context path
+pub fn compute_vec_diff(old: &Vec<GenericValue>, new: &Vec<GenericValue>, context_path: &Vec<DocIndex>, options: &DiffOptions) -> Vec<Hunk> {
+    if let Some(key) = options.key(context_path)
+        && let Some(updates) = compute_keyed_diff(old, new, context_path, key, options) {
+        return updates;
+    }
     let mut updates: Vec<Hunk> = Vec::new(); // resulting operations
     // workspace of indices in old, that are not yet used
     let mut workspace: Vec<Rc<RefCell<Idx>>> = Vec::with_capacity(old.len());
     // hash to list of weak references to indices in workspace
     let mut sources: HashMap<u64, Vec<Weak<RefCell<Idx>>>> = HashMap::new();
     for (i, v) in old.iter().enumerate() {
-        let hash = hs(v);
+        let hash = hs_with(v, options, || append_path(context_path, i));
         let r = Rc::new(RefCell::new(Idx::new(i, hash)));
         sources.entry(hash).or_insert_with(Vec::new).push(Rc::downgrade(&r));
         workspace.push(r);
@@ -59,7 +65,7 @@ pub fn compute_vec_diff(old: &Vec<GenericValue>, new: &Vec<GenericValue>, contex
 
     let mut targets: HashMap<u64, Vec<usize>> = HashMap::new();
     for (i, v) in new.iter().enumerate() {  // hash to (index, is_used and must not remove operation)
-        targets.entry(hs(v)).or_insert_with(Vec::new).push(i);
+        targets.entry(hs_with(v, options, || append_path(context_path, i))).or_insert_with(Vec::new).push(i);
     }
 
     // implement strategy based of function docs:
@@ -80,7 +86,7 @@ pub fn compute_vec_diff(old: &Vec<GenericValue>, new: &Vec<GenericValue>, contex
                 value: HunkAction::Insert(new_value.clone()),
             });
         } else {
-            let new_hash = hs(new_value);
+            let new_hash = hs_with(new_value, options, || append_path(context_path, work_index));
             let work_item = workspace[work_index].borrow().deref().clone();
             if work_item.hash != new_hash {
                 let path = append_path(context_path, work_index);
@@ -107,7 +113,7 @@ pub fn compute_vec_diff(old: &Vec<GenericValue>, new: &Vec<GenericValue>, contex
                         if work_index < old.len() {
                             debug_assert!(workspace.len() > work_index);
                             compare_apply(&old[work_item.original_idx], new_value, new_hash, path,
-                                          &mut updates, &mut workspace, &mut sources, work_index);
+                                          &mut updates, &mut workspace, &mut sources, work_index, options);
                         } else {
                             updates.push(Hunk { path, value: HunkAction::Insert(new_value.clone()) });
                         }
@@ -134,14 +140,15 @@ fn compare_apply(original: &GenericValue,
                  updates: &mut Vec<Hunk>,
                  workspace: &mut Vec<Rc<RefCell<Idx>>>,
                  sources: &mut HashMap<u64, Vec<Weak<RefCell<Idx>>>>,
-                 work_index: usize)
+                 work_index: usize,
+                 options: &DiffOptions)
 {
 
      if match original {
         GenericValue::Map(base_map) => {
             match new_value {
                 GenericValue::Map(new_map) => {
-                    let mut changes = min_map_changes(base_map, new_map, &path);
+                    let mut changes = min_map_changes(base_map, new_map, &path, options);
                     updates.append(&mut changes);
                     false
                 }
@@ -151,9 +158,13 @@ fn compare_apply(original: &GenericValue,
         GenericValue::StringValue(base_txt) => {
             match new_value {
                 GenericValue::StringValue(new_txt) => {
-                    let ops = crate::txt::diff(base_txt, new_txt);
-                    updates.push(Hunk { path: path.clone(), value: HunkAction::UpdateTxt(ops) });
-                    false
+                    match options.text(&path).diff(base_txt, new_txt) {
+                        Some(ops) => {
+                            updates.push(Hunk { path: path.clone(), value: HunkAction::UpdateTxt(ops) });
+                            false
+                        }
+                        None => true
+                    }
                 }
                 _ => true
             }
@@ -168,6 +179,62 @@ fn compare_apply(original: &GenericValue,
     workspace[work_index] = r;
 }
 
+/// match elements by the identity field value with remove, swap and insert operations,
+/// changes of the matched elements are nested hunks under the target index;
+/// None if an element is not a map with the key or the key value is not unique
+fn compute_keyed_diff(old: &[GenericValue], new: &[GenericValue], context_path: &Vec<DocIndex>, key: &str, options: &DiffOptions) -> Option<Vec<Hunk>> {
+    let old_ids = identities(old, context_path, key, options)?;
+    let new_ids = identities(new, context_path, key, options)?;
+    let targets: HashSet<&GenericValue> = new_ids.iter().collect();
+    let sources: HashMap<&GenericValue, usize> = old_ids.iter().enumerate().map(|(i, id)| (id, i)).collect();
+    let mut updates: Vec<Hunk> = Vec::new();
+
+    // remove elements absent in new, the rest of workspace is a subset of new ids
+    let mut workspace = old_ids.clone();
+    let mut work_index = 0;
+    while work_index < workspace.len() {
+        if targets.contains(&workspace[work_index]) {
+            work_index += 1;
+        } else {
+            updates.push(Hunk { path: append_path(context_path, work_index), value: HunkAction::Remove });
+            workspace.remove(work_index);
+        }
+    }
+
+    // put every element to the target index, as the elements before are already in place
+    for (work_index, id) in new_ids.iter().enumerate() {
+        let path = append_path(context_path, work_index);
+        match workspace[work_index..].iter().position(|w| w == id) {
+            Some(fi) => {
+                let fi = fi + work_index;
+                if fi != work_index {
+                    updates.push(Hunk { path: path.clone(), value: HunkAction::Swap(DocIndex::Idx(fi)) });
+                    workspace.swap(work_index, fi);
+                }
+                let mut changes = GenericValue::diff_with(&old[sources[id]], &new[work_index], &path, options);
+                updates.append(&mut changes);
+            }
+            None => {
+                updates.push(Hunk { path, value: HunkAction::Insert(new[work_index].clone()) });
+                workspace.insert(work_index, id.clone());
+            }
+        }
+    }
+    Some(updates)
+}
+
+/// the identity field values, equal if the same with the options, None if missing or duplicated
+fn identities(values: &[GenericValue], context_path: &Vec<DocIndex>, key: &str, options: &DiffOptions) -> Option<Vec<GenericValue>> {
+    let mut unique = HashSet::new();
+    values.iter().enumerate().map(|(i, v)| match v {
+        GenericValue::Map(m) => m.get(key).map(|k| {
+            let mut p = append_path(context_path, i);
+            p.push(DocIndex::Name(key.to_string()));
+            canonical(k, options, &mut p)
+        }).filter(|id| unique.insert(id.clone())),
+        _ => None,
+    }).collect()
+}
 
 fn append_path(path: &Vec<DocIndex>, index: usize) -> Vec<DocIndex> {
     let mut p = path.clone();
@@ -201,9 +268,9 @@ mod tests {
     use std::collections::HashMap;
     use std::rc::{Rc, Weak};
     use serde_json::json;
-    use crate::diff::Mismatch;
-    use crate::vec_processor::{compute_vec_diff, Idx};
-    use crate::generic::{GenericValue, Hunk, HunkAction, DocIndex, hs, from_str_vec};
+    use crate::diff::{DiffOptions, Mismatch, NumericMode};
+    use crate::vec_processor::{compute_vec_diff, identities, Idx};
+    use crate::generic::{GenericValue, Hunk, HunkAction, DocIndex, hs, from_json, from_str_vec};
     use crate::{MismatchDoc, MismatchDocMut};
 
     #[test]
@@ -213,7 +280,7 @@ mod tests {
             if let GenericValue::Array(new) = serde_json::from_value(
                 json!([1, "two", true, null, "five"])).unwrap() {
                 let context_path = vec![];
-                let diffs = compute_vec_diff(&old, &new, &context_path);
+                let diffs = compute_vec_diff(&old, &new, &context_path, &DiffOptions::default());
                 assert_eq!(diffs.len(), 0);
             }
         }
@@ -258,7 +325,7 @@ mod tests {
             if let GenericValue::Array(new) = serde_json::from_value(
                 json!(["1", "3", "4"])).unwrap() {
                 let context_path = vec![];
-                let diffs = compute_vec_diff(&old, &new, &context_path);
+                let diffs = compute_vec_diff(&old, &new, &context_path, &DiffOptions::default());
                 println!("{:?}", diffs);
                 assert_eq!(diffs.len(), 2);
                 assert_eq!(diffs[0], Hunk{path:vec![DocIndex::Idx(1)], value: HunkAction::Remove});
@@ -274,7 +341,7 @@ mod tests {
             if let GenericValue::Array(new) = serde_json::from_value(
                 json!(["1", "3", "4"])).unwrap() {
                 let context_path = vec![];
-                let diffs = compute_vec_diff(&old, &new, &context_path);
+                let diffs = compute_vec_diff(&old, &new, &context_path, &DiffOptions::default());
                 println!("{:?}", diffs);
                 assert_eq!(diffs.len(), 4);
                 assert_eq!(diffs[0], Hunk{path:vec![DocIndex::Idx(1)], value: HunkAction::Remove});
@@ -292,7 +359,7 @@ mod tests {
             if let GenericValue::Array(new) = serde_json::from_value(
                 json!(["1", "3", "two", "4"])).unwrap() {
                 let context_path = vec![];
-                let diffs = compute_vec_diff(&old, &new, &context_path);
+                let diffs = compute_vec_diff(&old, &new, &context_path, &DiffOptions::default());
                 println!("{:?}", diffs);
                 assert_eq!(diffs.len(), 2);
                 assert_eq!(diffs[0], Hunk{path:vec![DocIndex::Idx(1)], value: HunkAction::Swap(DocIndex::Idx(2))});
@@ -308,7 +375,7 @@ mod tests {
             if let GenericValue::Array(new) = serde_json::from_value(
                 json!(["1", "3", "4", "two"])).unwrap() {
                 let context_path = vec![];
-                let diffs = compute_vec_diff(&old, &new, &context_path);
+                let diffs = compute_vec_diff(&old, &new, &context_path, &DiffOptions::default());
                 println!("{:?}", diffs);
                 assert_eq!(diffs.len(), 3);
                 assert_eq!(diffs[0], Hunk{path:vec![DocIndex::Idx(1)], value: HunkAction::Swap(DocIndex::Idx(2))});
@@ -325,7 +392,7 @@ mod tests {
             if let GenericValue::Array(new) = serde_json::from_value(
                 json!(["1", "3", "five", "two"])).unwrap() {
                 let context_path = vec![];
-                let diffs = compute_vec_diff(&old, &new, &context_path);
+                let diffs = compute_vec_diff(&old, &new, &context_path, &DiffOptions::default());
                 println!("{:?}", diffs);
                 assert_eq!(diffs.len(), 3);
 
@@ -347,6 +414,46 @@ mod tests {
         assert_eq!(old, new);
     }
 
+    #[test]
+    fn test_compute_keyed_diff() {
+        let base = from_json(r#"{"users": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}, {"id": 3, "name": "c"}]}"#).unwrap();
+        let a = from_json(r#"{"users": [{"id": 3, "name": "c"}, {"id": 1, "name": "a1"}, {"id": 4, "name": "d"}]}"#).unwrap();
+        let options = DiffOptions::default().with_key("$.users[*].id").unwrap();
+        let patch = Mismatch::new_with(&base, &a, &options).unwrap();
+        assert!(patch.hunks().contains(&Hunk {
+            path: vec![DocIndex::Name("users".into()), DocIndex::Idx(1), DocIndex::Name("name".into())],
+            value: HunkAction::Update(GenericValue::StringValue("a1".into())),
+        }), "{:?}", patch);
+        let mut doc = base.clone();
+        patch.apply_tx(&mut doc).unwrap();
+        assert_eq!(doc, a);
+
+        // edit other field of other record does not intersect
+        let b = from_json(r#"{"users": [{"id": 1, "name": "a"}, {"id": 2, "name": "b"}, {"id": 3, "name": "c3"}]}"#).unwrap();
+        let patch_b = Mismatch::new_with(&base, &b, &options).unwrap();
+        assert_eq!(patch_b.len(), 1);
+        let patch_a = Mismatch::new_with(&base, &from_json(r#"{"users": [{"id": 1, "name": "a1"}, {"id": 2, "name": "b"}, {"id": 3, "name": "c"}]}"#).unwrap(), &options).unwrap();
+        assert!(!patch_a.is_intersect(&patch_b).unwrap());
+
+        // fallback to whole value match on duplicated id
+        let c = from_json(r#"{"users": [{"id": 1, "name": "a"}, {"id": 1, "name": "b"}]}"#).unwrap();
+        let patch = Mismatch::new_with(&base, &c, &options).unwrap();
+        assert_eq!(patch, Mismatch::new(&base, &c).unwrap());
+        assert!(DiffOptions::default().with_key("$.users.id").is_err());
+    }
 
+    #[test]
+    fn test_keyed_diff_identity_value() {
+        let base = from_json(r#"{"users": [{"id": 1, "name": "a"}, {"id": "1", "name": "b"}]}"#).unwrap();
+        let a = from_json(r#"{"users": [{"id": 1.0, "name": "a1"}, {"id": "1", "name": "b"}]}"#).unwrap();
+        let options = DiffOptions::default().with_key("$.users[*].id").unwrap().with_numeric(NumericMode::Value);
+        assert_eq!(identities(&[from_json("1").unwrap(), from_json("1.0").unwrap()], &vec![], "id", &options), None);
+        let patch = Mismatch::new_with(&base, &a, &options).unwrap();
+        assert!(patch.hunks().contains(&Hunk {
+            path: vec![DocIndex::Name("users".into()), DocIndex::Idx(0), DocIndex::Name("name".into())],
+            value: HunkAction::Update(GenericValue::StringValue("a1".into())),
+        }), "{:?}", patch);
+        assert_eq!(patch.len(), 1);
+    }
 
 }
@@ -0,0 +1,594 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use crate::DocError;
+use crate::generic::{GenericValue, Map};
+
+/// XML document with the prolog and epilog nodes around the root element.
+///
+/// The model keeps the infoset: elements, attributes, text, CDATA, comments, processing instructions,
+/// the document type and namespaces in the document order, so `parse` then `to_string` reads back the same.
+/// The source form is normalized on write: `<a></a>` as `<a/>`, the character and internal entity references
+/// as the characters with `&amp;`, `&lt;`, `&gt;` and `&quot;` in attributes escaped, attribute values in `"`,
+/// and no spaces inside the tags but between the attributes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlDocument {
+    /// content of the `<?xml ...?>` declaration, if present
+    pub declaration: Option<String>,
+    pub nodes: Vec<XmlNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    /// qualified name with the namespace prefix
+    pub name: String,
+    /// attributes in the document order, the namespace declarations included
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+/// node of the document in the document order
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlNode {
+    Element(Element),
+    Text(String),
+    CData(String),
+    Comment(String),
+    /// processing instruction target and data
+    Pi(String, String),
+    /// raw document type declaration
+    DocType(String),
+}
+
+const ORDER: &str = "#order";
+const ATTRIBUTES: &str = "#attrs";
+const DECLARATION: &str = "#declaration";
+const TEXT: &str = "#text";
+const CDATA: &str = "#cdata";
+const COMMENT: &str = "#comment";
+const DOCTYPE: &str = "#doctype";
+
+/// parse XML to the generic document value of the `XmlDocument` model
+pub fn from_str(s: &str) -> Result<GenericValue, DocError> {
+    Ok(XmlDocument::parse(s)?.to_value())
+}
+
+/// write the generic document value of the `XmlDocument` model as XML
+pub fn to_string(value: &GenericValue) -> Result<String, DocError> {
+    Ok(XmlDocument::from_value(value)?.to_string())
+}
+
+impl XmlDocument {
+    pub fn parse(s: &str) -> Result<Self, DocError> {
+        let mut reader = Reader { s: s.strip_prefix('\u{feff}').unwrap_or(s), pos: 0, entities: HashMap::new() };
+        let declaration = if reader.rest().starts_with("<?xml") && reader.rest()[5..].starts_with(|c: char| c.is_whitespace() || c == '?') {
+            reader.pos += 5;
+            Some(reader.until("?>")?.trim().to_string())
+        } else {
+            None
+        };
+        let nodes = reader.content(None)?;
+        let roots = nodes.iter().filter(|n| matches!(n, XmlNode::Element(_))).count();
+        let text = nodes.iter().any(|n| match n {
+            XmlNode::Text(t) => !t.trim().is_empty(),
+            XmlNode::CData(_) => true,
+            _ => false,
+        });
+        if roots != 1 || text {
+            return Err(DocError::new("XML document must have a single root element"));
+        }
+        Ok(XmlDocument { declaration, nodes })
+    }
+
+    /// map to the generic value: `@name` keys for attributes, child elements by name
+    /// as arrays to address by position, `#text`, `#cdata`, `#comment`, `#doctype` and `?target` arrays
+    /// for other nodes, `#order` array of the keys to keep the document order of mixed nodes,
+    /// and `#attrs` array of the attribute names if they are not sorted
+    pub fn to_value(&self) -> GenericValue {
+        let mut m = nodes_value(&self.nodes);
+        if let Some(d) = &self.declaration {
+            m.insert(DECLARATION.to_string(), GenericValue::StringValue(d.clone()));
+        }
+        GenericValue::Map(m)
+    }
+
+    pub fn from_value(value: &GenericValue) -> Result<Self, DocError> {
+        let GenericValue::Map(m) = value else {
+            return Err(DocError::new("XML document must be a map"));
+        };
+        let declaration = m.get(DECLARATION).map(text).transpose()?;
+        Ok(XmlDocument { declaration, nodes: nodes_from(m)? })
+    }
+}
+
+impl Element {
+    fn to_value(&self) -> GenericValue {
+        let mut m = nodes_value(&self.children);
+        for (k, v) in &self.attributes {
+            m.insert(format!("@{}", k), GenericValue::StringValue(v.clone()));
+        }
+        // the map keeps the attributes sorted, or in any order with `preserve_order`
+        if !self.attributes.is_sorted_by(|(a, _), (b, _)| a <= b) {
+            let names = self.attributes.iter().map(|(k, _)| GenericValue::StringValue(k.clone())).collect();
+            m.insert(ATTRIBUTES.to_string(), GenericValue::Array(names));
+        }
+        GenericValue::Map(m)
+    }
+
+    fn from_value(name: &str, value: &GenericValue) -> Result<Self, DocError> {
+        let GenericValue::Map(m) = value else {
+            return Err(DocError::new(format!("Element must be a map: {}", name)));
+        };
+        let mut attributes = m.iter()
+            .filter_map(|(k, v)| k.strip_prefix('@').map(|k| Ok((k.to_string(), text(v)?))))
+            .collect::<Result<Vec<_>, DocError>>()?;
+        if !cfg!(feature="preserve_order") {
+            attributes.sort(); // deterministic output of the unordered map
+        }
+        // the attributes in the `#attrs` sequence, the attributes out of it appended
+        let order: Vec<String> = match m.get(ATTRIBUTES) {
+            Some(GenericValue::Array(a)) => a.iter().map(text).collect::<Result<_, _>>()?,
+            Some(_) => return Err(DocError::new("XML attributes order must be an array")),
+            None => vec![],
+        };
+        for (i, name) in order.iter().enumerate() {
+            let at = attributes.iter().position(|(k, _)| k == name)
+                .filter(|at| *at >= i)
+                .ok_or_else(|| DocError::new(format!("XML attributes order refers to the missing attribute: {}", name)))?;
+            let attribute = attributes.remove(at);
+            attributes.insert(i, attribute);
+        }
+        Ok(Element { name: name.to_string(), attributes, children: nodes_from(m)? })
+    }
+}
+
+impl XmlNode {
+    /// key of the node group in the element map
+    fn key(&self) -> String {
+        match self {
+            XmlNode::Element(e) => e.name.clone(),
+            XmlNode::Text(_) => TEXT.to_string(),
+            XmlNode::CData(_) => CDATA.to_string(),
+            XmlNode::Comment(_) => COMMENT.to_string(),
+            XmlNode::Pi(target, _) => format!("?{}", target),
+            XmlNode::DocType(_) => DOCTYPE.to_string(),
+        }
+    }
+
+    fn from_value(key: &str, value: &GenericValue) -> Result<Self, DocError> {
+        Ok(match key {
+            TEXT => XmlNode::Text(text(value)?),
+            CDATA => XmlNode::CData(text(value)?),
+            COMMENT => XmlNode::Comment(text(value)?),
+            DOCTYPE => XmlNode::DocType(text(value)?),
+            k if k.starts_with('?') => XmlNode::Pi(k[1..].to_string(), text(value)?),
+            k => XmlNode::Element(Element::from_value(k, value)?),
+        })
+    }
+}
+
+fn nodes_value(nodes: &[XmlNode]) -> Map {
+    let mut m = Map::new();
+    let mut order = Vec::with_capacity(nodes.len());
+    for n in nodes {
+        let value = match n {
+            XmlNode::Element(e) => e.to_value(),
+            XmlNode::Text(s) | XmlNode::CData(s) | XmlNode::Comment(s) | XmlNode::Pi(_, s) | XmlNode::DocType(s) =>
+                GenericValue::StringValue(s.clone()),
+        };
+        let key = n.key();
+        if let GenericValue::Array(a) = m.entry(key.clone()).or_insert_with(|| GenericValue::Array(vec![])) {
+            a.push(value);
+        }
+        order.push(GenericValue::StringValue(key));
+    }
+    // single kind of nodes keeps the order in the array
+    if m.len() > 1 {
+        m.insert(ORDER.to_string(), GenericValue::Array(order));
+    }
+    m
+}
+
+/// nodes in the `#order` sequence, the nodes out of the order appended
+fn nodes_from(m: &Map) -> Result<Vec<XmlNode>, DocError> {
+    let mut keys: Vec<&String> = m.keys()
+        .filter(|k| !k.starts_with('@') && *k != ORDER && *k != ATTRIBUTES && *k != DECLARATION).collect();
+    if !cfg!(feature="preserve_order") {
+        keys.sort_unstable();
+    }
+    let mut order: Vec<String> = match m.get(ORDER) {
+        Some(GenericValue::Array(a)) => a.iter().map(text).collect::<Result<_, _>>()?,
+        Some(_) => return Err(DocError::new("XML order must be an array")),
+        None => vec![],
+    };
+    let mut nodes = Vec::new();
+    let mut used: HashMap<&str, usize> = HashMap::new(); // count of the nodes taken by key
+    for k in keys {
+        let n = order.iter().filter(|o| *o == k).count();
+        let len = match &m[k] {
+            GenericValue::Array(a) => a.len(),
+            _ => 1,
+        };
+        order.extend(std::iter::repeat_n(k.clone(), len.saturating_sub(n)));
+    }
+    for key in &order {
+        let idx = used.entry(key).or_default();
+        let value = match m.get(key) {
+            Some(GenericValue::Array(a)) => a.get(*idx),
+            Some(v) if *idx == 0 => Some(v),
+            _ => None,
+        }.ok_or_else(|| DocError::new(format!("XML order refers to the missing node: {}", key)))?;
+        *idx += 1;
+        nodes.push(XmlNode::from_value(key, value)?);
+    }
+    Ok(nodes)
+}
+
+/// text of the node, a numeric text may come from the patch json
+fn text(value: &GenericValue) -> Result<String, DocError> {
+    match value {
+        GenericValue::StringValue(s) => Ok(s.clone()),
+        GenericValue::Numeric(n) => Ok(n.0.clone()),
+        GenericValue::Boolean(b) => Ok(b.to_string()),
+        _ => Err(DocError::new(format!("Expected XML text: {:?}", value))),
+    }
+}
+
+struct Reader<'a> {
+    s: &'a str,
+    pos: usize,
+    /// replacement text of the general entities declared in the internal subset of the document type
+    entities: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Reader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn error(&self, msg: &str) -> DocError {
+        let line = self.s[..self.pos].matches('\n').count() + 1;
+        DocError::new(format!("XML {} at line {}", msg, line))
+    }
+
+    /// text up to the delimiter, the delimiter consumed
+    fn until(&mut self, delimiter: &str) -> Result<&'a str, DocError> {
+        let end = self.rest().find(delimiter).ok_or_else(|| self.error(&format!("expected '{}'", delimiter)))?;
+        let text = &self.rest()[..end];
+        self.pos += end + delimiter.len();
+        Ok(text)
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos += self.rest().len() - self.rest().trim_start().len();
+    }
+
+    fn name(&mut self) -> Result<&'a str, DocError> {
+        let end = self.rest().find(|c: char| c.is_whitespace() || "/>=?".contains(c)).unwrap_or(self.rest().len());
+        if end == 0 {
+            return Err(self.error("expected name"));
+        }
+        let name = &self.rest()[..end];
+        self.pos += end;
+        Ok(name)
+    }
+
+    /// nodes up to the end tag of the element or the end of the document
+    fn content(&mut self, element: Option<&str>) -> Result<Vec<XmlNode>, DocError> {
+        let mut nodes = Vec::new();
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return match element {
+                    Some(name) => Err(self.error(&format!("unclosed element '{}'", name))),
+                    None => Ok(nodes),
+                };
+            }
+            if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.until(">")?.trim();
+                return if Some(name) == element { Ok(nodes) } else { Err(self.error(&format!("unexpected end tag '{}'", name))) };
+            }
+            let node = if rest.starts_with("<!--") {
+                self.pos += 4;
+                XmlNode::Comment(self.until("-->")?.to_string())
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                XmlNode::CData(self.until("]]>")?.to_string())
+            } else if rest.starts_with("<!DOCTYPE") {
+                self.pos += 9;
+                let doctype = self.doctype()?;
+                self.entities = entities(doctype);
+                XmlNode::DocType(doctype.trim().to_string())
+            } else if rest.starts_with("<?") {
+                self.pos += 2;
+                let target = self.name()?.to_string();
+                XmlNode::Pi(target, self.until("?>")?.trim_start().to_string())
+            } else if rest.starts_with('<') {
+                self.pos += 1;
+                XmlNode::Element(self.element()?)
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = decode(&rest[..end], &self.entities, 0).map_err(|e| self.error(&e))?;
+                self.pos += end;
+                XmlNode::Text(text)
+            };
+            nodes.push(node);
+        }
+    }
+
+    /// document type up to the closing bracket, the internal subset included
+    fn doctype(&mut self) -> Result<&'a str, DocError> {
+        let rest = self.rest();
+        let mut depth = 0;
+        let mut quote = None;
+        for (i, c) in rest.char_indices() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '[') => depth += 1,
+                (None, ']') => depth -= 1,
+                (None, '>') if depth == 0 => {
+                    self.pos += i + 1;
+                    return Ok(&rest[..i]);
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("unclosed document type"))
+    }
+
+    fn element(&mut self) -> Result<Element, DocError> {
+        let name = self.name()?.to_string();
+        let mut attributes = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(Element { name, attributes, children: vec![] });
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                let children = self.content(Some(&name))?;
+                return Ok(Element { name, attributes, children });
+            }
+            let key = self.name()?.to_string();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(&format!("expected value of attribute '{}'", key)));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error(&format!("expected quoted value of attribute '{}'", key))),
+            };
+            self.pos += 1;
+            let value = self.until(&quote.to_string())?;
+            attributes.push((key, decode(value, &self.entities, 0).map_err(|e| self.error(&e))?));
+        }
+    }
+}
+
+/// the general entities with the literal value in the internal subset, the external and parameter ones skipped
+fn entities(doctype: &str) -> HashMap<&str, &str> {
+    let mut out = HashMap::new();
+    let Some(start) = doctype.find('[') else {
+        return out;
+    };
+    let mut rest = &doctype[start + 1..];
+    while let Some(i) = rest.find("<!") {
+        rest = &rest[i..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map(|c| c.1).unwrap_or_default();
+            continue;
+        }
+        rest = &rest[2..];
+        let Some(decl) = rest.strip_prefix("ENTITY") else {
+            continue;
+        };
+        let mut words = decl.trim_start().splitn(2, char::is_whitespace);
+        let (Some(name), Some(value)) = (words.next(), words.next().map(str::trim_start)) else {
+            continue;
+        };
+        if let Some(q @ ('"' | '\'')) = value.chars().next().filter(|_| name != "%")
+            && let Some(end) = value[1..].find(q) {
+            out.insert(name, &value[1..end + 1]);
+        }
+    }
+    out
+}
+
+/// limits of the nested entities and the expanded text, so the recursive entities do not explode
+const MAX_ENTITY_DEPTH: usize = 8;
+const MAX_EXPANSION: usize = 1 << 20;
+
+/// replace the predefined, character and internal entity references
+fn decode(s: &str, entities: &HashMap<&str, &str>, depth: usize) -> Result<String, String> {
+    if !s.contains('&') {
+        return Ok(s.to_string());
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        let end = rest[i..].find(';').ok_or_else(|| format!("unterminated reference '{}'", &rest[i..]))?;
+        let entity = &rest[i + 1..i + end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            e if e.starts_with("#x") => u32::from_str_radix(&e[2..], 16).ok().and_then(char::from_u32),
+            e if e.starts_with('#') => e[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match (c, entities.get(entity)) {
+            (Some(c), _) => out.push(c),
+            (None, Some(value)) if depth < MAX_ENTITY_DEPTH => {
+                let value = decode(value, entities, depth + 1)?;
+                if value.contains('<') {
+                    return Err(format!("entity with markup '&{};' is not supported", entity));
+                }
+                out.push_str(&value);
+            }
+            (None, Some(_)) => return Err(format!("entity '&{};' nested too deep", entity)),
+            (None, None) => return Err(format!("unknown reference '&{};'", entity)),
+        }
+        if out.len() > MAX_EXPANSION {
+            return Err(format!("entity '&{};' expands too large", entity));
+        }
+        rest = &rest[i + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn escape(s: &str, attribute: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl Display for XmlDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(d) = &self.declaration {
+            write!(f, "<?xml {}?>", d)?;
+        }
+        self.nodes.iter().try_for_each(|n| write!(f, "{}", n))
+    }
+}
+
+impl Display for XmlNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XmlNode::Element(e) => {
+                write!(f, "<{}", e.name)?;
+                for (k, v) in &e.attributes {
+                    write!(f, " {}=\"{}\"", k, escape(v, true))?;
+                }
+                if e.children.is_empty() {
+                    return write!(f, "/>");
+                }
+                write!(f, ">")?;
+                e.children.iter().try_for_each(|n| write!(f, "{}", n))?;
+                write!(f, "</{}>", e.name)
+            }
+            XmlNode::Text(s) => write!(f, "{}", escape(s, false)),
+            XmlNode::CData(s) => write!(f, "<![CDATA[{}]]>", s),
+            XmlNode::Comment(s) => write!(f, "<!--{}-->", s),
+            XmlNode::Pi(target, data) if data.is_empty() => write!(f, "<?{}?>", target),
+            XmlNode::Pi(target, data) => write!(f, "<?{} {}?>", target, data),
+            XmlNode::DocType(s) => write!(f, "<!DOCTYPE {}>", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::Mismatch;
+    use crate::generic::DocIndex;
+    use crate::generic::{from_json, GenericValue};
+    use crate::xml::{from_str, to_string, XmlDocument, XmlNode};
+    use crate::{MismatchDoc, MismatchDocMut};
+
+    const CATALOG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE catalog [<!ENTITY x "y">]>
+<?xml-stylesheet href="style.xsl"?>
+<catalog xmlns:b="urn:books">
+  <!-- books -->
+  <b:book id="1"><title>First &amp; best</title><note>see <em>this</em> one</note></b:book>
+  <b:book id="2"><title>Second</title><![CDATA[<raw>]]></b:book>
+</catalog>
+"#;
+
+    #[test]
+    fn test_xml_round_trip() {
+        let doc = XmlDocument::parse(CATALOG).unwrap();
+        assert_eq!(doc.to_string(), CATALOG);
+        assert_eq!(XmlDocument::from_value(&doc.to_value()).unwrap(), doc);
+        assert_eq!(to_string(&from_str(CATALOG).unwrap()).unwrap(), CATALOG);
+
+        let root = doc.nodes.iter().find_map(|n| match n {
+            XmlNode::Element(e) => Some(e),
+            _ => None,
+        }).unwrap();
+        assert_eq!(root.attributes, vec![("xmlns:b".to_string(), "urn:books".to_string())]);
+        assert!(XmlDocument::parse("<a><b></a>").is_err());
+        assert!(XmlDocument::parse("<a/><b/>").is_err());
+    }
+
+    /// the source form is normalized, the document reads back the same
+    #[test]
+    fn test_xml_normalized() {
+        let write = |s: &str| to_string(&from_str(s).unwrap()).unwrap();
+        for (source, written) in [
+            ("<a></a>", "<a/>"),
+            ("<a >x &#65;&#x42; &gt; &apos;&quot;</a >", "<a>x AB &gt; '\"</a>"),
+            ("<a y='2' z = \"it's\" />", "<a y=\"2\" z=\"it's\"/>"),
+            ("<!DOCTYPE a [<!-- <!ENTITY c 'x'> --><!ENTITY e \"e&#38;&amp;\"><!ENTITY f 'f&e;'>]><a t=\"&f;\">&e;&f;</a>",
+             "<!DOCTYPE a [<!-- <!ENTITY c 'x'> --><!ENTITY e \"e&#38;&amp;\"><!ENTITY f 'f&e;'>]><a t=\"fe&amp;&amp;\">e&amp;&amp;fe&amp;&amp;</a>"),
+        ] {
+            assert_eq!(write(source), written);
+            assert_eq!(from_str(written).unwrap(), from_str(source).unwrap());
+        }
+        // the attributes order whatever the features
+        assert_eq!(write("<a b=\"2\" a=\"1\" c=\"3\"/>"), "<a b=\"2\" a=\"1\" c=\"3\"/>");
+        let element = |v: &GenericValue, name: &str| match v {
+            GenericValue::Map(m) => match &m[name] {
+                GenericValue::Array(a) => a[0].clone(),
+                _ => panic!("{:?}", v),
+            },
+            _ => panic!("{:?}", v),
+        };
+        let a = element(&from_str("<a b=\"2\" a=\"1\"><c x=\"a\" y=\"b\"/></a>").unwrap(), "a");
+        let GenericValue::Map(m) = &a else { panic!("{:?}", a) };
+        assert_eq!(m["#attrs"], from_json(r#"["b", "a"]"#).unwrap());
+        assert_eq!(element(&a, "c"), from_json(r#"{"@x": "a", "@y": "b"}"#).unwrap());
+        assert!(to_string(&from_json(r##"{"a": [{"@x": "1", "#attrs": ["y"]}]}"##).unwrap()).is_err());
+        assert!(from_str("<a>&c;</a>").unwrap_err().to_string().contains("unknown reference '&c;'"));
+        assert!(from_str("<!DOCTYPE a [<!ENTITY m '<b/>'>]><a>&m;</a>").unwrap_err().to_string().contains("markup"));
+        assert!(from_str("<!DOCTYPE a [<!ENTITY r '&r;'>]><a>&r;</a>").unwrap_err().to_string().contains("too deep"));
+        let laughs = "<!DOCTYPE a [<!ENTITY l0 'lol'>".to_string() + &(1..8)
+            .map(|i| format!("<!ENTITY l{} '{}'>", i, format!("&l{};", i - 1).repeat(10))).collect::<String>() + "]><a>&l7;</a>";
+        assert!(from_str(&laughs).unwrap_err().to_string().contains("too large"));
+    }
+
+    #[test]
+    fn test_xml_diff() {
+        let base = from_str(CATALOG).unwrap();
+        let id = from_str(&CATALOG.replace(r#"id="1""#, r#"id="7""#)).unwrap();
+        let m = Mismatch::new(&base, &id).unwrap();
+        let book = |i: usize| vec![DocIndex::Name("catalog".into()), DocIndex::Idx(0), DocIndex::Name("b:book".into()), DocIndex::Idx(i)];
+        let mut path = book(0);
+        path.push(DocIndex::Name("@id".into()));
+        assert_eq!(m.hunks().iter().map(|h| h.path.clone()).collect::<Vec<_>>(), vec![path]);
+
+        let title = from_str(&CATALOG.replace("Second", "Other")).unwrap();
+        let t = Mismatch::new(&base, &title).unwrap();
+        assert!(t.hunks()[0].path.starts_with(&book(1)));
+        assert!(!m.is_intersect(&t).unwrap());
+        let other = from_str(&CATALOG.replace(r#"id="1""#, r#"id="8""#)).unwrap();
+        assert!(m.is_intersect(&Mismatch::new(&base, &other).unwrap()).unwrap());
+
+        // mixed content text node between elements
+        let mixed = from_str(&CATALOG.replace("see <em>", "look at <em>")).unwrap();
+        let x = Mismatch::new(&base, &mixed).unwrap();
+        let mut doc = base.clone();
+        x.apply_tx(&mut doc).unwrap();
+        t.apply_tx(&mut doc).unwrap();
+        assert_eq!(to_string(&doc).unwrap(), CATALOG.replace("see <em>", "look at <em>").replace("Second", "Other"));
+        assert!(x.hunks()[0].path.contains(&DocIndex::Name("#text".into())), "{}", x.hunks()[0]);
+    }
+}
@@ -0,0 +1,544 @@
+use crate::DocError;
+use crate::edit::Edit;
+use crate::generic::{to_yaml, DocIndex, GenericValue};
+use crate::json_patch::get;
+
+/// YAML source lines, the block mappings and sequences are edited in place by lines,
+/// scalars and flow collections are rewritten as a whole, the changed flow collection in the block style.
+///
+/// The supported subset is a single document, with optional `---`, `...` and `%` directive lines, of:
+///  - block mappings with plain or quoted keys and block sequences, the compact `- key: value` items too
+///  - plain, quoted and multi-line scalars, block scalars `|` and `>` with the chomping indicator
+///  - flow collections as a value, single or multi-line
+///  - comments, blank lines, tags, anchors and aliases kept, the nodes with anchors or aliases are not changed
+///
+/// The explicit `? key` entries, the block scalars with the indentation indicator as `|2` on the changed node
+/// and the lines out of the parsed block structure fail with the error, so the source is never rewritten
+/// beyond the changed nodes. The multi-document files are rejected by the YAML reader.
+pub(crate) struct YamlSource {
+    lines: Vec<String>,
+    /// CRLF line ends
+    cr: bool,
+    /// indentation step of the nested blocks
+    step: usize,
+}
+
+/// block structure of the source, the indent is a column of the keys or dashes
+enum Node {
+    Map(usize, Vec<Entry>),
+    Seq(usize, Vec<Entry>),
+    Leaf,
+}
+
+/// map entry or sequence item
+struct Entry {
+    key: Option<String>,
+    line: usize,
+    /// column of the key or dash
+    col: usize,
+    /// column after the colon or dash
+    value_col: usize,
+    node: Node,
+    /// last line of the value
+    end: usize,
+}
+
+impl YamlSource {
+    pub(crate) fn new(source: &str) -> Result<Self, DocError> {
+        let mut yaml = YamlSource { lines: source.split('\n').map(String::from).collect(), cr: source.contains("\r\n"), step: 2 };
+        let parser = Parser::new(&yaml.lines);
+        if let Some(first) = parser.next_content(None) {
+            let (root, end) = parser.parse_block(first, parser.indent(first), None);
+            if let Some(line) = parser.next_content(Some(end)) {
+                let key = is_explicit_key(parser.text(line, parser.indent(line)));
+                return Err(unsupported(line, if key { "explicit key" } else { "the line is out of the block structure" }));
+            }
+            let root_key = matches!(root, Node::Leaf) && is_explicit_key(parser.text(first, parser.indent(first)));
+            if let Some(line) = root_key.then_some(first).or_else(|| explicit_key(&parser, &root)) {
+                return Err(unsupported(line, "explicit key"));
+            }
+            yaml.step = step(&root).unwrap_or(2);
+        }
+        Ok(yaml)
+    }
+
+    pub(crate) fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+
+    /// rewrite the source lines on the path, the doc is already changed by the hunk
+    pub(crate) fn edit(&mut self, edit: &Edit, doc: &GenericValue) -> Result<(), DocError> {
+        match edit {
+            Edit::Set(path) => self.set(path, doc),
+            Edit::Remove(path) => self.remove(path, doc),
+            Edit::Insert(path) => self.insert(path, doc),
+            Edit::Move(path, to) => self.move_key(path, *to, doc),
+        }
+    }
+
+    fn parse(&self) -> Node {
+        let parser = Parser::new(&self.lines);
+        match parser.next_content(None) {
+            Some(line) => parser.parse_block(line, parser.indent(line), None).0,
+            None => Node::Leaf,
+        }
+    }
+
+    fn set(&mut self, path: &[DocIndex], doc: &GenericValue) -> Result<(), DocError> {
+        let value = get(doc, path).ok_or_else(|| not_found(path))?;
+        let root = self.parse();
+        match find(&root, path) {
+            Ok((Some(e), _)) => self.replace(e, value),
+            Ok((None, _)) => self.replace_root(value),
+            Err(k) => match (find(&root, &path[..k]), &path[k]) {
+                (Ok((_, Node::Map(col, es))), DocIndex::Name(key)) if k + 1 == path.len() && !es.iter().any(|e| self.is_compact(e)) => {
+                    let at = es.last().map(|e| e.end + 1).unwrap_or_default();
+                    let lines = self.entry_lines(*col, key, value)?;
+                    self.lines.splice(at..at, lines);
+                    Ok(())
+                }
+                _ => self.set(&path[..k], doc),
+            },
+        }
+    }
+
+    fn remove(&mut self, path: &[DocIndex], doc: &GenericValue) -> Result<(), DocError> {
+        let Some((_, parent)) = path.split_last() else {
+            return Ok(());
+        };
+        let root = self.parse();
+        match (find(&root, path), find(&root, parent)) {
+            (Ok((Some(e), _)), Ok((_, Node::Map(_, es) | Node::Seq(_, es)))) if es.len() > 1 && !self.is_compact(e) => {
+                self.check_anchors(e.line, e.col, e.end)?;
+                let start = self.attached(e);
+                self.lines.drain(start..=e.end);
+                Ok(())
+            }
+            // the element is absent already
+            (Err(k), Ok((_, Node::Map(..) | Node::Seq(..)))) if k == parent.len() => Ok(()),
+            (Err(k), _) if k < parent.len() => self.set(&path[..k], doc),
+            _ => self.set(parent, doc),
+        }
+    }
+
+    fn insert(&mut self, path: &[DocIndex], doc: &GenericValue) -> Result<(), DocError> {
+        let Some((DocIndex::Idx(i), parent)) = path.split_last() else {
+            return self.set(path, doc);
+        };
+        let value = get(doc, path).ok_or_else(|| not_found(path))?;
+        let root = self.parse();
+        match find(&root, parent) {
+            Ok((_, Node::Seq(col, items))) if *i <= items.len() && !items.iter().any(|e| self.is_compact(e)) => {
+                let at = match items.get(*i) {
+                    Some(e) => self.attached(e),
+                    None => items.last().map(|e| e.end + 1).unwrap_or_default(),
+                };
+                let lines = self.item_lines(*col, value)?;
+                self.lines.splice(at..at, lines);
+                Ok(())
+            }
+            Err(k) => self.set(&parent[..k], doc),
+            _ => self.set(parent, doc),
+        }
+    }
+
+    fn move_key(&mut self, path: &[DocIndex], to: usize, doc: &GenericValue) -> Result<(), DocError> {
+        let Some((_, parent)) = path.split_last() else {
+            return Ok(());
+        };
+        let root = self.parse();
+        match (find(&root, path), find(&root, parent)) {
+            (Ok((Some(e), _)), Ok((_, Node::Map(_, es)))) if !es.iter().any(|x| self.is_compact(x)) => {
+                self.check_anchors(e.line, e.col, e.end)?;
+                let rest: Vec<&Entry> = es.iter().filter(|x| x.line != e.line).collect();
+                let start = self.attached(e);
+                let at = match rest.get(to) {
+                    Some(x) => self.attached(x),
+                    None => rest.last().map(|x| x.end + 1).unwrap_or(start),
+                };
+                let block: Vec<String> = self.lines.drain(start..=e.end).collect();
+                let at = if at > e.end { at - block.len() } else { at };
+                self.lines.splice(at..at, block);
+                Ok(())
+            }
+            (Err(k), _) if k < parent.len() => self.set(&path[..k], doc),
+            _ => self.set(parent, doc),
+        }
+    }
+
+    /// replace the value of the entry, the key and the comment on the entry line kept
+    fn replace(&mut self, e: &Entry, value: &GenericValue) -> Result<(), DocError> {
+        self.check_anchors(e.line, e.value_col, e.end)?;
+        let line = &self.lines[e.line];
+        let end = content_end(line).max(e.value_col);
+        let old = line[e.value_col..end].trim_start();
+        if is_indented_scalar(old) {
+            return Err(unsupported(e.line, "block scalar with the indentation indicator"));
+        }
+        let quote = old.chars().next()
+            .filter(|q| e.end == e.line && matches!(e.node, Node::Leaf) && (*q == '"' || *q == '\'') && old.len() > 1 && old.ends_with(*q));
+        let head = &line[..e.value_col];
+        let tail = &line[end..];
+        let rendered = render(value, quote)?;
+        let mut lines = Vec::with_capacity(rendered.len());
+        if e.key.is_some() && is_block(value) {
+            let col = match &e.node {
+                Node::Map(c, _) | Node::Seq(c, _) if *c > e.col || matches!(value, GenericValue::Array(_)) => *c,
+                _ => e.col + self.step,
+            };
+            lines.push(format!("{}{}", head, tail));
+            lines.extend(rendered.iter().map(|r| self.indented(col, r)));
+        } else {
+            let col = if e.key.is_some() { e.col + self.step } else { e.col + 2 };
+            lines.push(format!("{} {}{}", head, rendered[0], tail));
+            lines.extend(rendered[1..].iter().map(|r| self.indented(col, r)));
+        }
+        self.lines.splice(e.line..=e.end, lines);
+        Ok(())
+    }
+
+    fn replace_root(&mut self, value: &GenericValue) -> Result<(), DocError> {
+        let parser = Parser::new(&self.lines);
+        let first = parser.next_content(None);
+        let last = (0..self.lines.len()).rev().find(|l| parser.content[*l].is_some());
+        let rendered: Vec<String> = render(value, None)?.iter().map(|r| self.indented(0, r)).collect();
+        match (first, last) {
+            (Some(first), Some(last)) => {
+                self.check_anchors(first, 0, last)?;
+                self.lines.splice(first..=last, rendered);
+            }
+            _ => {
+                let at = self.lines.len() - usize::from(self.lines.last().is_some_and(|l| l.is_empty()));
+                self.lines.splice(at..at, rendered);
+            }
+        }
+        Ok(())
+    }
+
+    fn entry_lines(&self, col: usize, key: &str, value: &GenericValue) -> Result<Vec<String>, DocError> {
+        let key = render(&GenericValue::StringValue(key.to_string()), None)?.concat();
+        let rendered = render(value, None)?;
+        let mut lines = Vec::with_capacity(rendered.len() + 1);
+        if is_block(value) {
+            lines.push(self.indented(col, &format!("{}:", key)));
+            lines.extend(rendered.iter().map(|r| self.indented(col + self.step, r)));
+        } else {
+            lines.push(self.indented(col, &format!("{}: {}", key, rendered[0])));
+            lines.extend(rendered[1..].iter().map(|r| self.indented(col + self.step, r)));
+        }
+        Ok(lines)
+    }
+
+    fn item_lines(&self, col: usize, value: &GenericValue) -> Result<Vec<String>, DocError> {
+        let rendered = render(value, None)?;
+        let mut lines = vec![self.indented(col, &format!("- {}", rendered[0]))];
+        lines.extend(rendered[1..].iter().map(|r| self.indented(col + 2, r)));
+        Ok(lines)
+    }
+
+    fn indented(&self, col: usize, text: &str) -> String {
+        let cr = if self.cr { "\r" } else { "" };
+        if text.is_empty() {
+            cr.to_string()
+        } else {
+            format!("{}{}{}", " ".repeat(col), text, cr)
+        }
+    }
+
+    /// the entry starts on the line of the parent sequence dash
+    fn is_compact(&self, e: &Entry) -> bool {
+        indent(&self.lines[e.line]) != e.col
+    }
+
+    /// first line of the entry including the comment lines just above
+    fn attached(&self, e: &Entry) -> usize {
+        let mut start = e.line;
+        while start > 0 && self.lines[start - 1].trim_start().starts_with('#') {
+            start -= 1;
+        }
+        start
+    }
+
+    /// anchors and aliases change the meaning of the other nodes, so they are not rewritten
+    fn check_anchors(&self, line: usize, col: usize, end: usize) -> Result<(), DocError> {
+        for l in line..=end {
+            let text = &self.lines[l];
+            let from = if l == line { col } else { 0 };
+            let text = &text[from.min(text.len())..content_end(text).max(from)];
+            let anchor = text.split(|c: char| c.is_whitespace() || ",[{".contains(c))
+                .any(|w| (w.starts_with('&') || w.starts_with('*')) && w[1..].starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+            if anchor {
+                return Err(DocError::new(format!("Anchor or alias at the changed line: {}", l + 1)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the entry and node of the path, or the length of the existing path prefix
+fn find<'a>(root: &'a Node, path: &[DocIndex]) -> Result<(Option<&'a Entry>, &'a Node), usize> {
+    let mut entry = None;
+    let mut node = root;
+    for (k, p) in path.iter().enumerate() {
+        let next = match (node, p) {
+            (Node::Map(_, es), DocIndex::Name(n)) => es.iter().find(|e| e.key.as_deref() == Some(n.as_str())),
+            (Node::Seq(_, es), DocIndex::Idx(i)) => es.get(*i),
+            _ => None,
+        };
+        let e = next.ok_or(k)?;
+        entry = Some(e);
+        node = &e.node;
+    }
+    Ok((entry, node))
+}
+
+/// indentation of the first nested block
+fn step(node: &Node) -> Option<usize> {
+    match node {
+        Node::Map(_, es) | Node::Seq(_, es) => es.iter().find_map(|e| match &e.node {
+            Node::Map(c, _) | Node::Seq(c, _) if e.key.is_some() && *c > e.col => Some(c - e.col),
+            n => step(n),
+        }),
+        Node::Leaf => None,
+    }
+}
+
+fn is_block(value: &GenericValue) -> bool {
+    match value {
+        GenericValue::Map(m) => !m.is_empty(),
+        GenericValue::Array(a) => !a.is_empty(),
+        _ => false,
+    }
+}
+
+/// value lines, a single line string keeps the quotes of the replaced value;
+/// the lines are indented by the caller, so the block scalar with the indentation indicator is not rendered
+fn render(value: &GenericValue, quote: Option<char>) -> Result<Vec<String>, DocError> {
+    let text = match (value, quote) {
+        (GenericValue::StringValue(s), Some('"')) if !s.contains('\n') =>
+            serde_json::to_string(s).map_err(|e| DocError::new(e.to_string()))?,
+        (GenericValue::StringValue(s), Some('\'')) if !s.contains('\n') => format!("'{}'", s.replace('\'', "''")),
+        _ => to_yaml(value).map_err(|e| DocError::new(e.to_string()))?,
+    };
+    let lines: Vec<String> = text.trim_end_matches('\n').split('\n').map(String::from).collect();
+    if lines.iter().any(|l| l.rsplit(' ').next().is_some_and(is_indented_scalar)) {
+        return Err(DocError::new("Unsupported YAML: block scalar with the indentation indicator in the new value"));
+    }
+    Ok(lines)
+}
+
+/// the block scalar header with the indentation indicator, as `|2`, `>-1` or `|+2 # comment`
+fn is_indented_scalar(text: &str) -> bool {
+    text.strip_prefix(['|', '>']).is_some_and(|h| {
+        let h = h.split(" #").next().unwrap_or_default().trim_end();
+        h.chars().any(|c| c.is_ascii_digit()) && h.chars().all(|c| c.is_ascii_digit() || c == '+' || c == '-')
+    })
+}
+
+fn is_explicit_key(text: &str) -> bool {
+    text == "?" || text.starts_with("? ")
+}
+
+/// the line of the `? key` entry as the sequence item, the other block entries end at it
+fn explicit_key(parser: &Parser, node: &Node) -> Option<usize> {
+    match node {
+        Node::Leaf => None,
+        Node::Map(_, es) | Node::Seq(_, es) => es.iter().find_map(|e| match &e.node {
+            Node::Leaf if e.key.is_none() => {
+                let text = parser.text(e.line, e.value_col).trim_start();
+                let line = if text.is_empty() { parser.next_content(Some(e.line)).filter(|l| *l <= e.end)? } else { e.line };
+                is_explicit_key(parser.text(line, parser.indent(line)).trim_start_matches(['-', ' '])).then_some(line)
+            }
+            n => explicit_key(parser, n),
+        }),
+    }
+}
+
+fn unsupported(line: usize, what: &str) -> DocError {
+    DocError::new(format!("Unsupported YAML at line {}: {}", line + 1, what))
+}
+
+fn not_found(path: &[DocIndex]) -> DocError {
+    DocError::new(format!("Path not found: {:?}", path))
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// end of the line content before a comment and trailing spaces
+fn content_end(line: &str) -> usize {
+    let b = line.as_bytes();
+    let mut quote = None;
+    let mut end = 0;
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i];
+        match quote {
+            Some(b'"') if c == b'\\' => i += 1,
+            Some(b'\'') if c == b'\'' && b.get(i + 1) == Some(&b'\'') => i += 1,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == b'#' && (i == 0 || b[i - 1] == b' ' || b[i - 1] == b'\t') => break,
+            None if (c == b'"' || c == b'\'') && (i == 0 || b" \t:-[{,".contains(&b[i - 1])) => quote = Some(c),
+            None => {}
+        }
+        if !c.is_ascii_whitespace() || quote.is_some() {
+            end = (i + 1).min(b.len());
+        }
+        i += 1;
+    }
+    end
+}
+
+fn is_dash(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// only an anchor or a tag, the value is a block on the next lines
+fn is_properties(text: &str) -> bool {
+    text.split_whitespace().all(|w| w.starts_with('&') || w.starts_with('!'))
+}
+
+/// the key and the position of the colon
+fn key_sep(text: &str) -> Option<(String, usize)> {
+    let b = text.as_bytes();
+    let colon = |i: usize| b.get(i) == Some(&b':') && b.get(i + 1).is_none_or(|c| *c == b' ' || *c == b'\t');
+    match b.first()? {
+        q @ (b'"' | b'\'') => {
+            let mut i = 1;
+            while i < b.len() {
+                match b[i] {
+                    b'\\' if *q == b'"' => i += 1,
+                    b'\'' if *q == b'\'' && b.get(i + 1) == Some(&b'\'') => i += 1,
+                    c if c == *q => break,
+                    _ => {}
+                }
+                i += 1;
+            }
+            let quoted = text.get(..=i)?;
+            let sep = i + 1 + (text[i + 1..].len() - text[i + 1..].trim_start().len());
+            if !colon(sep) {
+                return None;
+            }
+            let key = if *q == b'"' {
+                serde_json::from_str(quoted).ok()?
+            } else {
+                quoted[1..quoted.len() - 1].replace("''", "'")
+            };
+            Some((key, sep))
+        }
+        b'[' | b'{' | b'?' | b'|' | b'>' | b'*' | b'&' | b'!' | b'%' | b'@' | b'`' => None,
+        _ => {
+            let sep = (0..b.len()).find(|i| colon(*i))?;
+            let key = text[..sep].trim_end();
+            (!key.is_empty()).then(|| (key.to_string(), sep))
+        }
+    }
+}
+
+/// line based parser of the block structure
+struct Parser<'a> {
+    lines: &'a [String],
+    /// indent and content end of the lines with content
+    content: Vec<Option<(usize, usize)>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(lines: &'a [String]) -> Self {
+        let content = lines.iter().map(|l| {
+            let (indent, end) = (indent(l), content_end(l));
+            let text = l.get(indent..end).unwrap_or_default();
+            (end > indent && text != "---" && text != "..." && !text.starts_with('%')).then_some((indent, end))
+        }).collect();
+        Parser { lines, content }
+    }
+
+    fn next_content(&self, after: Option<usize>) -> Option<usize> {
+        let from = after.map(|l| l + 1).unwrap_or_default();
+        (from..self.lines.len()).find(|l| self.content[*l].is_some())
+    }
+
+    fn indent(&self, line: usize) -> usize {
+        self.content[line].map(|c| c.0).unwrap_or_default()
+    }
+
+    fn text(&self, line: usize, col: usize) -> &'a str {
+        let end = self.content[line].map(|c| c.1).unwrap_or_default();
+        self.lines[line].get(col..end).unwrap_or_default()
+    }
+
+    fn parse_block(&self, line: usize, col: usize, parent: Option<usize>) -> (Node, usize) {
+        let text = self.text(line, col);
+        if is_dash(text) {
+            self.parse_seq(line, col)
+        } else if key_sep(text).is_some() {
+            self.parse_map(line, col)
+        } else {
+            (Node::Leaf, self.leaf_end(line, parent))
+        }
+    }
+
+    /// last line of the scalar or flow value, the continuation lines are indented more than the parent
+    fn leaf_end(&self, line: usize, parent: Option<usize>) -> usize {
+        let mut end = line;
+        while let Some(next) = self.next_content(Some(end)) {
+            if parent.is_some_and(|p| self.indent(next) <= p) {
+                break;
+            }
+            end = next;
+        }
+        end
+    }
+
+    /// nested block of the entry on the next lines
+    fn child(&self, line: usize, col: usize, is_key: bool) -> (Node, usize) {
+        match self.next_content(Some(line)) {
+            Some(next) if self.indent(next) > col => self.parse_block(next, self.indent(next), Some(col)),
+            // the sequence of the key may have the same indent
+            Some(next) if is_key && self.indent(next) == col && is_dash(self.text(next, col)) => self.parse_seq(next, col),
+            _ => (Node::Leaf, line),
+        }
+    }
+
+    fn parse_map(&self, first: usize, col: usize) -> (Node, usize) {
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut line = first;
+        while let Some((key, sep)) = key_sep(self.text(line, col)) {
+            let rest = self.text(line, col)[sep + 1..].trim_start();
+            let (node, end) = if rest.is_empty() || is_properties(rest) {
+                self.child(line, col, true)
+            } else {
+                (Node::Leaf, self.leaf_end(line, Some(col)))
+            };
+            entries.push(Entry { key: Some(key), line, col, value_col: col + sep + 1, node, end });
+            match self.next_content(Some(end)) {
+                Some(next) if self.indent(next) == col && !is_dash(self.text(next, col)) => line = next,
+                _ => break,
+            }
+        }
+        let end = entries.last().map(|e| e.end).unwrap_or(first);
+        (Node::Map(col, entries), end)
+    }
+
+    fn parse_seq(&self, first: usize, col: usize) -> (Node, usize) {
+        let mut items: Vec<Entry> = Vec::new();
+        let mut line = first;
+        loop {
+            let rest = &self.text(line, col)[1..];
+            let value = rest.trim_start();
+            let (node, end) = if value.is_empty() || is_properties(value) {
+                self.child(line, col, false)
+            } else {
+                self.parse_block(line, col + 1 + rest.len() - value.len(), Some(col))
+            };
+            items.push(Entry { key: None, line, col, value_col: col + 1, node, end });
+            match self.next_content(Some(end)) {
+                Some(next) if self.indent(next) == col && is_dash(self.text(next, col)) => line = next,
+                _ => break,
+            }
+        }
+        let end = items.last().map(|e| e.end).unwrap_or(first);
+        (Node::Seq(col, items), end)
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs::{read_to_string, write};
+use std::process::{Command, Output};
+use diff_doc::generic::from_json;
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_diff-doc")).args(args).output().unwrap()
+}
+
+/// the temp file path, the file is removed on drop
+struct Tmp(String);
+
+impl std::ops::Deref for Tmp {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for Tmp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn tmp(name: &str, content: &[u8]) -> Tmp {
+    let path = std::env::temp_dir().join(format!("diff-doc-{}-{}", std::process::id(), name));
+    write(&path, content).unwrap();
+    Tmp(path.to_string_lossy().to_string())
+}
+
+#[test]
+fn test_cli_json() {
+    let dir = "tests/json/case3";
+    let pa = run(&["diff", &format!("{}/base.json", dir), &format!("{}/a.json", dir)]);
+    assert!(pa.status.success(), "{}", String::from_utf8_lossy(&pa.stderr));
+    let pb = run(&["diff", &format!("{}/base.json", dir), &format!("{}/b.json", dir)]);
+    let (pa, pb) = (tmp("a.json", &pa.stdout), tmp("b.json", &pb.stdout));
+
+    let applied = run(&["apply", &format!("{}/base.json", dir), &pa]);
+    assert!(applied.status.success(), "{}", String::from_utf8_lossy(&applied.stderr));
+    assert_eq!(from_json(&String::from_utf8_lossy(&applied.stdout)).unwrap(),
+               from_json(&read_to_string(format!("{}/a.json", dir)).unwrap()).unwrap());
+
+    assert_eq!(run(&["check", &pa, &pb]).status.code(), Some(0));
+    assert_eq!(run(&["check", &pa, &pa]).status.code(), Some(0), "same changes");
+
+    let merged = run(&["merge", &format!("{}/base.json", dir), &format!("{}/a.json", dir), &format!("{}/b.json", dir)]);
+    assert_eq!(merged.status.code(), Some(0), "{}", String::from_utf8_lossy(&merged.stderr));
+    assert_eq!(from_json(&String::from_utf8_lossy(&merged.stdout)).unwrap(),
+               from_json(&read_to_string(format!("{}/result.json", dir)).unwrap()).unwrap());
+}
+
+#[test]
+fn test_cli_txt() {
+    let dir = "tests/txt/case1";
+    let merged = run(&["merge", &format!("{}/base.txt", dir), &format!("{}/a.txt", dir), &format!("{}/b.txt", dir)]);
+    assert_eq!(merged.status.code(), Some(0), "{}", String::from_utf8_lossy(&merged.stderr));
+    assert_eq!(String::from_utf8_lossy(&merged.stdout), read_to_string(format!("{}/result.txt", dir)).unwrap());
+
+    let pa = tmp("ta.json", &run(&["diff", &format!("{}/base.txt", dir), &format!("{}/a.txt", dir)]).stdout);
+    let pc = tmp("tc.json", &run(&["diff", &format!("{}/base.txt", dir), &format!("{}/b.txt", dir)]).stdout);
+    assert_eq!(run(&["check", &pa, &pc]).status.code(), Some(0));
+    let doc = run(&["apply", &format!("{}/base.json", "tests/json/case1"), &pa]);
+    assert_eq!(doc.status.code(), Some(2), "patch type mismatch");
+    assert_eq!(run(&["diff"]).status.code(), Some(2), "usage");
+}
+
+#[test]
+fn test_cli_yaml() {
+    let base = tmp("base.yaml", b"# settings\nname: demo  # the name\nlimits:\n  cpu: 1\n");
+    let new = tmp("new.yaml", b"name: demo\nlimits:\n  cpu: 2\n");
+    let patch = tmp("y.json", &run(&["diff", &base, &new]).stdout);
+    let applied = run(&["apply", &base, &patch]);
+    assert!(applied.status.success(), "{}", String::from_utf8_lossy(&applied.stderr));
+    assert_eq!(String::from_utf8_lossy(&applied.stdout), "# settings\nname: demo  # the name\nlimits:\n  cpu: 2\n");
+}
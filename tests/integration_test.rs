@@ -1,5 +1,6 @@
 mod json;
 mod txt;
+mod cli;
 #[cfg(feature="patch")] mod patch_diff;
 
 #[test]
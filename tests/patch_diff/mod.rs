@@ -54,6 +54,211 @@ fn test_txt_diff__1() {
 }
 
 
+#[test]
+fn test_patch_apply() {
+    use diff_doc::patch::{ApplyOptions, HunkStatus, Mismatch};
+    let base: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+    let input = base.replace("line 3\n", "line three\n").replace("line 16\n", "").replace("line 20\n", "line 20");
+    let patch = Mismatch::new(&base, &input).unwrap();
+    assert_eq!(patch.apply(&base).unwrap(), input);
+    assert_eq!(patch.apply_with(&base, &ApplyOptions::default()).unwrap(),
+        (input.clone(), vec![HunkStatus::Applied, HunkStatus::Applied]));
+
+    // two lines added on top moves both hunks
+    let moved = format!("a\nb\n{}", base);
+    let (text, status) = patch.apply_with(&moved, &ApplyOptions::default()).unwrap();
+    assert_eq!(text, format!("a\nb\n{}", input));
+    assert_eq!(status, vec![HunkStatus::Offset(2), HunkStatus::Offset(2)]);
+    assert_eq!(patch.apply_with(&moved, &ApplyOptions::new(1, 2)).unwrap().1, vec![HunkStatus::Rejected, HunkStatus::Rejected]);
+
+    // the changed context line is ignored by the fuzz, the removed line is not
+    let fuzzy = base.replace("line 1\n", "line one\n").replace("line 13\n", "line thirteen\n");
+    let (text, status) = patch.apply_with(&fuzzy, &ApplyOptions::default()).unwrap();
+    assert_eq!(text, input.replace("line 1\n", "line one\n").replace("line 13\n", "line thirteen\n"));
+    assert_eq!(status, vec![HunkStatus::Fuzz { offset: 0, fuzz: 1 }, HunkStatus::Fuzz { offset: 0, fuzz: 1 }]);
+    assert_eq!(patch.apply_with(&fuzzy, &ApplyOptions::new(0, 0)).unwrap().1, vec![HunkStatus::Rejected, HunkStatus::Rejected]);
+
+    let rejected = base.replace("line 3\n", "line 3.0\n");
+    let (text, status) = patch.apply_with(&rejected, &ApplyOptions::default()).unwrap();
+    assert_eq!(text, input.replace("line three\n", "line 3.0\n"));
+    assert_eq!(status, vec![HunkStatus::Rejected, HunkStatus::Applied]);
+    assert!(patch.apply(&rejected).is_err());
+
+    // the lines are counted from 1, only the empty range starts at 0
+    let zero: Mismatch = serde_json::from_str(r#""--- a\n+++ b\n@@ -0,1 +0,1 @@\n-line 1\n+one\n""#).unwrap();
+    assert!(zero.apply_with(&base, &ApplyOptions::default()).unwrap_err().to_string().contains("starts at line 0"));
+    assert!(zero.to_txt(&base).is_err());
+    assert!(zero.is_intersect(&patch).is_err());
+    assert!(patch.is_intersect(&zero).is_err());
+}
+
+#[test]
+fn test_multi_file_patch() {
+    use diff_doc::patch::MultiMismatch;
+    let base = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    let git = r#"commit message
+
+---
+ src/a.rs | 2 +-
+
+diff --git a/src/a.rs b/src/a.rs
+index 3b18e51..a3c2b4e 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,4 +1,4 @@
+ 1
+-2
++-- two
+ 3
+ 4
+diff --git a/old name.txt b/new name.txt
+similarity index 90%
+rename from old name.txt
+rename to new name.txt
+--- a/old name.txt
++++ b/new name.txt
+@@ -10 +10 @@
+-10
++ten
+\ No newline at end of file
+diff --git a/new.txt b/new.txt
+new file mode 100644
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++x
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100755
+index 587be6b..0000000
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+diff --git a/logo.png b/logo.png
+index 1..2 100644
+GIT binary patch
+literal 3
+KcmZ?wxB>t4
+
+"#;
+    let a = MultiMismatch::parse(git).unwrap();
+    let files = a.files();
+    assert_eq!(files.len(), 6);
+    assert_eq!(files.iter().map(|f| f.path()).collect::<Vec<_>>(), vec!["src/a.rs", "old name.txt", "new.txt", "gone.txt", "run.sh", "logo.png"]);
+    assert_eq!(files[0].patch.apply(&base.to_string()).unwrap(), base.replace("2\n", "-- two\n"));
+    assert_eq!(files[1].new_path.as_deref(), Some("new name.txt"));
+    assert_eq!(files[1].patch.apply(&base.to_string()).unwrap(), base.replace("10\n", "ten"));
+    assert_eq!((files[2].old_path.as_deref(), files[2].new_mode.as_deref()), (None, Some("100644")));
+    assert_eq!(files[2].patch.apply(&String::new()).unwrap(), "x\n");
+    assert_eq!((files[3].new_path.as_deref(), files[3].old_mode.as_deref()), (None, Some("100755")));
+    assert_eq!((files[4].old_mode.as_deref(), files[4].new_mode.as_deref(), files[4].patch.len()), (Some("100644"), Some("100755"), 0));
+    assert!(files[5].binary);
+
+    // plain `diff -ruN` output without git headers
+    let plain = "--- src/a.rs\t2024-01-01 00:00:00\n+++ src/a.rs\t2024-01-02 00:00:00\n@@ -8,3 +8,3 @@\n 8\n-9\n+nine\n 10\n\
+--- /dev/null\n+++ other.txt\n@@ -0,0 +1 @@\n+y\n";
+    let b = MultiMismatch::parse(plain).unwrap();
+    assert_eq!(b.files().iter().map(|f| (f.old_path.as_deref(), f.new_path.as_deref())).collect::<Vec<_>>(),
+        vec![(Some("src/a.rs"), Some("src/a.rs")), (None, Some("other.txt"))]);
+    assert!(!a.is_intersect(&b).unwrap());
+
+    let c = MultiMismatch::parse("--- a/src/a.rs\n+++ b/src/a.rs\n@@ -2 +2 @@\n-2\n+2.0\n\
+--- a/gone.txt\n+++ b/gone.txt\n@@ -2 +2 @@\n-2\n+2.0\n\
+--- a/run.sh\n+++ b/run.sh\n@@ -1 +1 @@\n-1\n+#!/bin/sh\n\
+--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+z\n").unwrap();
+    assert_eq!(c.len(), 4);
+    assert_eq!(a.intersections(&c).unwrap(), vec!["src/a.rs", "new.txt", "gone.txt"]);
+    assert!(MultiMismatch::parse("--- a\n+++ b\n@@ -1,2 +1,2 @@\n 1\n").is_err());
+}
+
+#[test]
+fn test_patch_intersect() {
+    use diff_doc::patch::{ApplyOptions, HunkStatus, Mismatch};
+    let base: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+    let patch = |input: String| Mismatch::new(&base, &input).unwrap();
+    let a = patch(base.replace("line 3\n", "line 3\na\nb\n").replace("line 12\n", "twelve\n"));
+    let b = patch(base.replace("line 16\n", "line 16\nc\n").replace("line 25\n", ""));
+    assert!(!a.is_intersect(&b).unwrap());
+    assert!(!b.is_intersect(&a).unwrap());
+    // no intersection, so the strict apply takes them in any order
+    let both = |x: &Mismatch, y: &Mismatch| y.apply(&x.apply(&base).unwrap()).unwrap();
+    assert_eq!(both(&a, &b), both(&b, &a));
+    let after = patch(base.replace("line 7\n", "line 7\n7.5\n"));
+    assert!(!a.is_intersect(&after).unwrap());
+    assert_eq!(both(&a, &after), both(&after, &a));
+
+    // the change of the context line of the other hunk, the strict apply does not match it
+    let short: String = (1..=10).map(|i| format!("line {}\n", i)).collect();
+    let three = Mismatch::new(&short, &short.replace("line 3\n", "three\n")).unwrap();
+    let six = Mismatch::new(&short, &short.replace("line 6\n", "six\n")).unwrap();
+    assert!(three.is_intersect(&six).unwrap());
+    assert!(six.is_intersect(&three).unwrap());
+    assert!(six.apply(&three.apply(&short).unwrap()).is_err());
+    let ten = Mismatch::new(&short, &short.replace("line 10\n", "ten\n")).unwrap();
+    assert!(!three.is_intersect(&ten).unwrap());
+    assert_eq!(ten.apply(&three.apply(&short).unwrap()).unwrap(), three.apply(&ten.apply(&short).unwrap()).unwrap());
+    assert!(a.is_intersect(&patch(base.replace("line 5\n", "line 5\n5.5\n"))).unwrap());
+
+    // the same change is not the intersection, `apply_with` finds it in the base
+    let same = patch(base.replace("line 12\n", "twelve\n"));
+    assert!(!a.is_intersect(&same).unwrap());
+    assert!(!same.is_intersect(&a).unwrap());
+    assert!(same.apply(&a.apply(&base).unwrap()).is_err());
+    let (text, status) = same.apply_with(&a.apply(&base).unwrap(), &ApplyOptions::default()).unwrap();
+    assert_eq!((text, status), (a.apply(&base).unwrap(), vec![HunkStatus::Present]));
+    // the change between the changes of the hunk, `apply_with` applies them one by one
+    let around = patch(base.replace("line 20\n", "twenty\n").replace("line 24\n", "twenty four\n"));
+    let between = patch(base.replace("line 22\n", "twenty two\n"));
+    assert!(around.is_intersect(&between).unwrap());
+    let (text, status) = around.apply_with(&between.apply(&base).unwrap(), &ApplyOptions::default()).unwrap();
+    assert_eq!(status, vec![HunkStatus::Split]);
+    assert_eq!(text, between.apply_with(&around.apply(&base).unwrap(), &ApplyOptions::default()).unwrap().0);
+    // the changes of the adjacent and the same lines
+    assert!(a.is_intersect(&patch(base.replace("line 13\n", "13.0\n"))).unwrap());
+    assert!(a.is_intersect(&patch(base.replace("line 11\nline 12\nline 13\n", "11-13\n"))).unwrap());
+    assert!(a.is_intersect(&patch(base.replace("line 3\n", "line 3\nb\na\n"))).unwrap());
+}
+
+#[test]
+fn test_patch_txt_convert() {
+    use diff_doc::patch::Mismatch;
+    use diff_doc::txt::{self, DiffOp, Granularity, Algorithm};
+    let base = "one\ntwo\nthree\nfour\nfive\n".to_string();
+    let input = "one\ntwo 2\nthree\nfour\nfive\nsix".to_string();
+    let t = txt::Mismatch::new_with(&base, &input, Granularity::Word, Algorithm::Myers).unwrap();
+    let p = Mismatch::from_txt(&t, &base, 1).unwrap();
+    let text = "--- original\n+++ modified\n@@ -1,3 +1,3 @@\n one\n-two\n+two 2\n three\n\
+        @@ -5 +5,2 @@\n five\n+six\n\\ No newline at end of file\n";
+    assert_eq!(p.to_string(), text);
+    // the received diff text
+    let received: Mismatch = text.parse().unwrap();
+    assert_eq!(received, p);
+    assert_eq!(received.to_txt(&base).unwrap(), p.to_txt(&base).unwrap());
+    assert!("@@ -1,3 +1,3 @@\n one\n".parse::<Mismatch>().is_err());
+    assert!("@@ -0,1 +0,1 @@\n-one\n+1\n".parse::<Mismatch>().is_err());
+    assert_eq!(p.apply(&base).unwrap(), input);
+    assert_eq!(p.to_txt(&base).unwrap().0, vec![DiffOp::Update { index: 1, value: "two 2".to_string() },
+        DiffOp::Update { index: 5, value: "six".to_string() }]);
+    assert_eq!(Mismatch::from_txt(&t, &base, 0).unwrap().to_txt(&base).unwrap().apply(&base).unwrap(), input);
+
+    // the CRLF lines and the removed final newline
+    let base = "a\r\nb\r\nc\r\n".to_string();
+    let input = "a\r\nB\r\nc".to_string();
+    let p = Mismatch::new(&base, &input).unwrap();
+    let t = p.to_txt(&base).unwrap();
+    assert_eq!(t.0, vec![DiffOp::Update { index: 1, value: "B".to_string() }, DiffOp::Remove { index: 3 }]);
+    assert_eq!(t.apply(&base).unwrap(), input);
+    assert_eq!(Mismatch::from_txt(&t, &base, 3).unwrap(), p);
+    // the unified diffs take part in the check of the text patches
+    let other = Mismatch::new(&base, &"A\r\nb\r\nc\r\n".to_string()).unwrap().to_txt(&base).unwrap();
+    assert!(!t.is_intersect(&other).unwrap());
+    assert_eq!(other.rebase(&t).unwrap().apply(&input).unwrap(), "A\r\nB\r\nc");
+    // the lone CR splits the lines of the text patch only
+    let input = "a\rb\r\nc\r\n".to_string();
+    let p = Mismatch::new(&base, &input).unwrap();
+    assert_eq!(p.to_txt(&base).unwrap().apply(&base).unwrap(), input);
+    assert!(p.to_txt("x\r\nb\r\nc\r\n").is_err());
+}
+
 
 fn irange(h: &HunkRange, diff: &mut HashSet<usize>) -> usize {
     let range = max(h.len(), 7);
@@ -33,7 +33,22 @@ fn test_case1() {
 
 #[test]
 fn test_case2() {
-    test_case(2, 1, 3);
+    // B inserts "new line" and the empty last line, see test_case2_ops
+    test_case(2, 1, 2);
+}
+
+/// the lines after the last line without the end-of-line: the new line and the empty last line
+/// of the text ending with "\n" are inserted, the end-of-line of the last base line goes with them
+#[test]
+fn test_case2_ops() {
+    let base = read_to_string(format!("{}/case2/base.txt", BASE)).unwrap();
+    let b = read_to_string(format!("{}/case2/b.txt", BASE)).unwrap();
+    assert!(!base.ends_with('\n') && b.ends_with("another line\nnew line\n"));
+    let pb = Mismatch::new(&base, &b).unwrap();
+    assert_eq!(serde_json::to_string(&pb).unwrap(),
+        r#"[{"Insert":{"index":3,"value":"new line"}},{"Insert":{"index":4,"value":""}}]"#);
+    assert_eq!(pb.apply(&base).unwrap(), b);
+    assert_eq!(pb.invert(&base).unwrap().apply(&b).unwrap(), base);
 }
 
 #[test]